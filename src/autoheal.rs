@@ -0,0 +1,497 @@
+// AutoHeal - works out how to top the whole party up from the field menu (MenuState::AutoHeal)
+// The planner only looks at numbers (HP missing, TP left, items in the bag) and returns a list of steps,
+// which are shown to the player first and then carried out through the normal field skill/item code
+// Only living members get healed - reviving is left to the player
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+use crate::components::{character::Character, party::{Party, PlayerParty, Row}, HealthEvents};
+use crate::items::*;
+use crate::resources::*;
+use crate::skills::*;
+use crate::textbox::*;
+use crate::turn::TurnPhase;
+
+// What one TP is worth next to item prices, so skills and items can be weighed against each other
+const TP_PRICE: i32 = 10;
+// Safety net on plan length - each step has to heal something, so this is never hit with sane data
+const MAX_PLAN_STEPS: usize = 100;
+// How many step swaps the planner tries on top of its greedy plan
+const MAX_SWAP_TRIES: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealSource {
+    Skill { caster: usize, skill: String },
+    Item { item: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealStep {
+    pub source: HealSource,
+    pub targets: Vec<usize>,  // Party slots
+    pub healed: i32,          // HP this step actually restores (Overhealing not counted)
+    pub cost: i32,            // TP or item price, converted with TP_PRICE
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HealPlan {
+    pub steps: Vec<HealStep>,
+    pub tp_spent: BTreeMap<usize, i32>,  // Party slot -> TP used
+    pub items_used: BTreeMap<String, u32>,
+    pub missing_before: i32,  // Total HP missing across the living party
+    pub missing_after: i32,   // Whatever the plan couldn't cover
+}
+
+impl HealPlan {
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn total_cost(&self) -> i32 {
+        self.steps.iter().map(|s| s.cost).sum()
+    }
+
+    // Lines for the confirmation box
+    pub fn summary(&self, party: &Party, skills: &SkillLibrary, items: &ItemLibrary) -> Vec<String> {
+        let name = |slot: &usize| party.members.get(*slot).map(|m| m.name().to_string()).unwrap_or_default();
+        let mut lines: Vec<String> = self.steps.iter()
+            .map(|step| {
+                let targets: Vec<String> = step.targets.iter().map(name).collect();
+                let what = match &step.source {
+                    HealSource::Skill { caster, skill } => format!("{}: {}", name(caster), skills.get(skill).map(|s| s.name.as_str()).unwrap_or(skill)),
+                    HealSource::Item { item } => items.name(item).to_string(),
+                };
+                format!("{} -> {} (+{})", what, targets.join(", "), step.healed)
+            })
+            .collect();
+        for (slot, tp) in &self.tp_spent {
+            lines.push(format!("{} uses {} TP", name(slot), tp));
+        }
+        for (item, qty) in &self.items_used {
+            lines.push(format!("Uses {} x{}", items.name(item), qty));
+        }
+        if self.missing_after > 0 {
+            lines.push(format!("{} HP can't be restored", self.missing_after));
+        }
+        lines
+    }
+}
+
+// Skills and items only count if all they do is heal - no burning a Refresh to top someone up
+fn heals_only(effects: &[SkillEffect]) -> bool {
+    !effects.is_empty() && effects.iter().all(|e| matches!(e, SkillEffect::Heal { .. }))
+}
+
+// Every group of living members a targeting could hit, given who's using it
+fn target_groups(targeting: Targeting, user: Option<usize>, party: &Party) -> Vec<Vec<usize>> {
+    let living: Vec<usize> = party.members.iter().enumerate()
+        .filter(|(_, m)| m.character.is_alive())
+        .map(|(i, _)| i)
+        .collect();
+    match (targeting.side, targeting.scope) {
+        (TargetSide::Enemy, _) => Vec::new(),
+        (TargetSide::User, _) => user.map(|u| vec![vec![u]]).unwrap_or_default(),
+        (_, TargetScope::Single) => living.iter().map(|i| vec![*i]).collect(),
+        (_, TargetScope::Row) => [Row::Front, Row::Back].iter()
+            .map(|row| living.iter().copied().filter(|i| party.members[*i].row == *row).collect::<Vec<usize>>())
+            .filter(|group| !group.is_empty())
+            .collect(),
+        (_, TargetScope::All) => vec![living],
+    }
+}
+
+struct Candidate {
+    source: HealSource,
+    targets: Vec<usize>,
+    amount: i32,  // Per target
+    tp: i32,      // TP per use for skills, 0 for items
+    cost: i32,
+}
+
+// Where the party would be after some set of steps - `chosen` indexes into the candidate list
+#[derive(Clone)]
+struct HealState {
+    missing: Vec<i32>,
+    tp: Vec<i32>,
+    stock: BTreeMap<String, u32>,
+    cost: i32,
+    chosen: Vec<usize>,
+}
+
+impl HealState {
+    fn can_use(&self, c: &Candidate) -> bool {
+        match &c.source {
+            HealSource::Skill { caster, .. } => self.tp[*caster] >= c.tp,
+            HealSource::Item { item } => self.stock.get(item).copied().unwrap_or(0) > 0,
+        }
+    }
+
+    fn healed(&self, c: &Candidate) -> i32 {
+        c.targets.iter().map(|t| c.amount.min(self.missing[*t])).sum()
+    }
+
+    fn apply(&mut self, c: &Candidate, index: usize) {
+        for t in &c.targets {
+            self.missing[*t] -= c.amount.min(self.missing[*t]);
+        }
+        match &c.source {
+            HealSource::Skill { caster, .. } => self.tp[*caster] -= c.tp,
+            HealSource::Item { item } => *self.stock.entry(item.clone()).or_default() -= 1,
+        }
+        self.cost += c.cost;
+        self.chosen.push(index);
+    }
+
+    // Lower is better - HP left missing first, then what it cost
+    fn score(&self) -> (i32, i32) {
+        (self.missing.iter().sum(), self.cost)
+    }
+}
+
+// Greedy - keeps picking whichever affordable step restores the most missing HP per point of cost
+// until everyone is full or nothing useful is left. Since overhealing doesn't count towards the score,
+// a big heal on a small wound scores badly and the cheaper option gets picked
+fn greedy_fill(candidates: &[Candidate], state: &mut HealState) {
+    while state.chosen.len() < MAX_PLAN_STEPS {
+        let best = candidates.iter().enumerate()
+            .filter(|(_, c)| state.can_use(c))
+            .map(|(i, c)| (i, c, state.healed(c)))
+            .filter(|(_, _, h)| *h > 0)
+            .max_by(|(_, a, ha), (_, b, hb)| {
+                (*ha as i64 * b.cost.max(1) as i64).cmp(&(*hb as i64 * a.cost.max(1) as i64)).then(ha.cmp(hb))
+            });
+        let Some((i, c, _)) = best else { break };
+        state.apply(c, i);
+    }
+}
+
+// Runs a list of steps from the start, skipping any that can't be afforded or wouldn't heal anything by then
+fn replay(candidates: &[Candidate], start: &HealState, steps: &[usize]) -> HealState {
+    let mut state = start.clone();
+    for i in steps {
+        if state.can_use(&candidates[*i]) && state.healed(&candidates[*i]) > 0 {
+            state.apply(&candidates[*i], *i);
+        }
+    }
+    state
+}
+
+// Tops a plan up greedily, then drops any step (Priciest first) the party can do without -
+// a step can go if every member it heals would still end up just as healed without it
+fn finish(candidates: &[Candidate], start: &HealState, steps: &[usize]) -> HealState {
+    let mut state = replay(candidates, start, steps);
+    greedy_fill(candidates, &mut state);
+    // HP each member is getting beyond what they're missing
+    let mut spare: Vec<i32> = start.missing.iter().map(|m| -m).collect();
+    for i in &state.chosen {
+        for t in &candidates[*i].targets {
+            spare[*t] += candidates[*i].amount;
+        }
+    }
+    let mut steps = state.chosen.clone();
+    steps.sort_by_key(|i| -candidates[*i].cost);
+    steps.retain(|i| {
+        let c = &candidates[*i];
+        if c.targets.iter().any(|t| spare[*t] < c.amount) {
+            return true;
+        }
+        c.targets.iter().for_each(|t| spare[*t] -= c.amount);
+        false
+    });
+    replay(candidates, start, &steps)
+}
+
+// Greedy plan, then a swap pass - try trading one step for another candidate and keep whatever comes out cheaper
+// (Or heals more). Catches the usual greedy misses, like two small potions where one big one would do.
+// Capped at MAX_SWAP_TRIES so planning never holds up a frame - it's a good plan, not a guaranteed cheapest one
+fn plan_steps(candidates: &[Candidate], start: &HealState) -> HealState {
+    let mut best = finish(candidates, start, &[]);
+    let mut tries = 0;
+    'improve: loop {
+        for k in 0..best.chosen.len() {
+            for j in 0..candidates.len() {
+                if j == best.chosen[k] {
+                    continue;
+                }
+                if tries == MAX_SWAP_TRIES {
+                    break 'improve;
+                }
+                tries += 1;
+                let mut steps = best.chosen.clone();
+                steps[k] = j;
+                let swapped = finish(candidates, start, &steps);
+                if swapped.score() < best.score() {
+                    best = swapped;
+                    continue 'improve;
+                }
+            }
+        }
+        break;
+    }
+    best
+}
+
+pub fn plan_autoheal(party: &Party, inventory: &Inventory, skills: &SkillLibrary, items: &ItemLibrary) -> HealPlan {
+    let members: Vec<&Character> = party.members.iter().map(|m| &m.character).collect();
+    let start = HealState {
+        missing: members.iter()
+            .map(|c| if c.is_alive() { c.health.max_hp() - c.health.hp() } else { 0 })
+            .collect(),
+        tp: members.iter().map(|c| c.tp).collect(),
+        stock: inventory.items.iter().map(|s| (s.item.clone(), s.qty)).collect(),
+        cost: 0,
+        chosen: Vec::new(),
+    };
+
+    // Everything that could possibly help, before checking what's still affordable
+    let mut candidates = Vec::new();
+    for (slot, caster) in members.iter().enumerate().filter(|(_, c)| c.is_alive()) {
+        let user = SkillUser::from_character(caster);
+        for (id, level) in &caster.skills {
+            let Some(def) = skills.get(id) else { continue };
+            if !def.usable.in_field() || !heals_only(&def.effects) {
+                continue;
+            }
+            let amount = heal_amount(&def.effects, *level, &user);
+            for targets in target_groups(def.target, Some(slot), party) {
+                let source = HealSource::Skill { caster: slot, skill: id.clone() };
+                candidates.push(Candidate { source, targets, amount, tp: def.cost, cost: def.cost * TP_PRICE });
+            }
+        }
+    }
+    for (def, _) in inventory.usable(items, false) {
+        if !heals_only(&def.effects) {
+            continue;
+        }
+        let amount = heal_amount(&def.effects, 1, &item_user(def));
+        for targets in target_groups(def.target, None, party) {
+            let source = HealSource::Item { item: def.id.clone() };
+            candidates.push(Candidate { source, targets, amount, tp: 0, cost: def.value as i32 });
+        }
+    }
+    candidates.retain(|c| c.amount > 0);
+
+    // Replayed in order so each step only reports the HP it actually restores
+    let best = plan_steps(&candidates, &start);
+    let mut state = start.clone();
+    let mut plan = HealPlan { missing_before: start.missing.iter().sum(), ..Default::default() };
+    for i in best.chosen {
+        let c = &candidates[i];
+        let healed = state.healed(c);
+        state.apply(c, i);
+        match &c.source {
+            HealSource::Skill { caster, .. } => *plan.tp_spent.entry(*caster).or_default() += c.tp,
+            HealSource::Item { item } => *plan.items_used.entry(item.clone()).or_default() += 1,
+        }
+        plan.steps.push(HealStep { source: c.source.clone(), targets: c.targets.clone(), healed, cost: c.cost });
+    }
+    plan.missing_after = state.missing.iter().sum();
+    plan
+}
+
+// Carries the plan out step by step through the regular field use code
+pub fn apply_autoheal(
+    plan: &HealPlan,
+    members: &mut [Character],
+    inventory: &mut Inventory,
+    skills: &SkillLibrary,
+    items: &ItemLibrary,
+    rng: &mut GameRng,
+) -> Result<Vec<String>, String> {
+    let mut log = Vec::new();
+    for step in &plan.steps {
+        let lines = match &step.source {
+            HealSource::Skill { caster, skill } => {
+                let def = skills.get(skill).ok_or(format!("Unknown skill {}", skill))?;
+                use_skill_in_field(members, *caster, def, &step.targets, rng)?
+            }
+            HealSource::Item { item } => {
+                let def = items.get(item).ok_or(format!("Unknown item {}", item))?;
+                use_item_in_field(inventory, members, def, &step.targets, rng)?
+            }
+        };
+        log.extend(lines);
+    }
+    Ok(log)
+}
+
+// Plan waiting on the player's go-ahead
+#[derive(Resource, Debug, Clone)]
+pub struct PendingAutoHeal(pub HealPlan);
+
+const AUTOHEAL_OPTIONS: [&str; 2] = ["Heal", "Cancel"];
+
+// H while exploring opens the AutoHeal menu
+pub fn open_autoheal(
+    input: Res<Input<KeyCode>>,
+    mut next_gameplay: ResMut<NextState<GameplayState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    if input.just_pressed(KeyCode::H) {
+        next_gameplay.set(GameplayState::Menu);
+        next_menu.set(MenuState::AutoHeal);
+    }
+}
+
+// OnEnter(MenuState::AutoHeal) - plans and asks for confirmation
+pub fn autoheal_prompt(
+    mut commands: Commands,
+    party: Query<(&Party, &Inventory), With<PlayerParty>>,
+    skills: Res<SkillLibrary>,
+    items: Res<ItemLibrary>,
+) {
+    let Ok((party, inventory)) = party.get_single() else { return };
+    let plan = plan_autoheal(party, inventory, &skills, &items);
+    if plan.is_empty() {
+        let text = if plan.missing_before == 0 { "Everyone is already at full HP." } else { "Nothing on hand can heal the party." };
+        spawn_textbox(&mut commands, Some("AutoHeal"), text, &[]);
+    } else {
+        let text = plan.summary(party, &skills, &items).join("\n");
+        let options: Vec<String> = AUTOHEAL_OPTIONS.iter().map(|o| o.to_string()).collect();
+        spawn_textbox(&mut commands, Some("AutoHeal"), &text, &options);
+    }
+    commands.insert_resource(PendingAutoHeal(plan));
+}
+
+// MenuState::AutoHeal - applies the plan if accepted, then goes back to exploring either way
+pub fn autoheal_confirm(
+    mut commands: Commands,
+    pending: Res<PendingAutoHeal>,
+    mut inputs: EventReader<TextBoxInput>,
+    boxes: Query<Entity, With<TextBox>>,
+    mut party: Query<(Entity, &mut Party, &mut Inventory), With<PlayerParty>>,
+    skills: Res<SkillLibrary>,
+    items: Res<ItemLibrary>,
+    mut rng: ResMut<GameRng>,
+    mut health_events: HealthEvents,
+    mut next_gameplay: ResMut<NextState<GameplayState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    let Some(input) = inputs.read().last().copied() else { return };
+    let accepted = match input {
+        TextBoxInput::Confirm if pending.0.is_empty() => false,
+        TextBoxInput::Choose(0) if !pending.0.is_empty() => true,
+        TextBoxInput::Choose(1) if !pending.0.is_empty() => false,
+        _ => return,
+    };
+    if accepted {
+        if let Ok((entity, mut party, mut inventory)) = party.get_single_mut() {
+            // Worked on a copy so HP changes can go out through the usual health events
+            let mut members: Vec<Character> = party.members.iter().map(|m| m.character.clone()).collect();
+            match apply_autoheal(&pending.0, &mut members, &mut inventory, &skills, &items, &mut rng) {
+                Ok(log) => log.iter().for_each(|line| println!("{}", line)),
+                Err(e) => println!("AutoHeal stopped early - {}", e),
+            }
+            for (slot, healed) in members.into_iter().enumerate() {
+                let member = &mut party.members[slot].character;
+                health_events.set_hp(&mut member.health, healed.health.hp(), entity, Some(slot));
+                member.tp = healed.tp;
+            }
+        }
+    }
+    despawn_textbox(&mut commands, &boxes);
+    commands.remove_resource::<PendingAutoHeal>();
+    next_menu.set(MenuState::UpperView);
+    next_gameplay.set(GameplayState::Exploration);
+}
+
+pub struct AutoHealPlugin;
+
+impl Plugin for AutoHealPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, open_autoheal.in_set(TurnPhase::Input))
+            .add_systems(OnEnter(MenuState::AutoHeal), autoheal_prompt)
+            .add_systems(Update, autoheal_confirm
+                .after(textbox_input)
+                .run_if(in_state(MenuState::AutoHeal))
+                .run_if(resource_exists::<PendingAutoHeal>()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, targets: Vec<usize>, amount: i32, cost: i32) -> Candidate {
+        Candidate { source: HealSource::Item { item: id.to_string() }, targets, amount, tp: 0, cost }
+    }
+
+    fn start(missing: Vec<i32>, stock: &[(&str, u32)]) -> HealState {
+        HealState {
+            tp: vec![0; missing.len()],
+            missing,
+            stock: stock.iter().map(|(id, qty)| (id.to_string(), *qty)).collect(),
+            cost: 0,
+            chosen: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn picks_cheapest_over_best_ratio() {
+        // Greedy takes A twice (Better HP per cost) for 20, one B covers it for 13
+        let candidates = vec![item("a", vec![0], 40, 10), item("b", vec![0], 50, 13)];
+        let best = plan_steps(&candidates, &start(vec![50], &[("a", 5), ("b", 5)]));
+        assert_eq!(best.score(), (0, 13));
+        assert_eq!(best.chosen, vec![1]);
+    }
+
+    #[test]
+    fn heals_what_it_can_without_enough_items() {
+        let candidates = vec![item("a", vec![0], 40, 10), item("a", vec![1], 40, 10)];
+        let best = plan_steps(&candidates, &start(vec![100, 30], &[("a", 2)]));
+        // Both potions go on the big wound, the 30 HP one is left over
+        assert_eq!(best.score(), (50, 20));
+        assert_eq!(best.chosen, vec![0, 0]);
+    }
+
+    #[test]
+    fn group_heal_beats_singles() {
+        let mut candidates: Vec<Candidate> = (0..3).map(|i| item("a", vec![i], 30, 10)).collect();
+        candidates.push(item("all", vec![0, 1, 2], 30, 25));
+        let best = plan_steps(&candidates, &start(vec![30, 30, 30], &[("a", 9), ("all", 1)]));
+        assert_eq!(best.score(), (0, 25));
+    }
+
+    #[test]
+    fn full_party_plans_within_a_frame() {
+        // Two healers with a single, big and party heal each, plus a bag of potions
+        let skill = |caster: usize, skill: &str, targets: Vec<usize>, amount: i32, tp: i32| Candidate {
+            source: HealSource::Skill { caster, skill: skill.to_string() }, targets, amount, tp, cost: tp * TP_PRICE,
+        };
+        let mut candidates = Vec::new();
+        for caster in [1, 3] {
+            for t in 0..5 {
+                candidates.push(skill(caster, "heal", vec![t], 60, 4));
+                candidates.push(skill(caster, "big_heal", vec![t], 150, 9));
+            }
+            candidates.push(skill(caster, "heal_all", (0..5).collect(), 50, 14));
+        }
+        for t in 0..5 {
+            candidates.push(item("potion", vec![t], 50, 15));
+            candidates.push(item("hi_potion", vec![t], 150, 40));
+        }
+        candidates.push(item("mega_potion", (0..5).collect(), 100, 120));
+        let mut state = start(vec![777, 777, 777, 777, 777], &[("potion", 20), ("hi_potion", 10), ("mega_potion", 3)]);
+        state.tp[1] = 80;
+        state.tp[3] = 60;
+
+        let timer = std::time::Instant::now();
+        let plan = plan_steps(&candidates, &state);
+        assert!(timer.elapsed() < std::time::Duration::from_millis(16), "took {:?}", timer.elapsed());
+        let mut greedy = state.clone();
+        greedy_fill(&candidates, &mut greedy);
+        assert!(plan.score() <= greedy.score());
+    }
+
+    #[test]
+    fn skills_stop_when_tp_runs_out() {
+        let heal = Candidate { source: HealSource::Skill { caster: 0, skill: "heal".to_string() }, targets: vec![0], amount: 20, tp: 5, cost: 5 * TP_PRICE };
+        let mut state = start(vec![60], &[]);
+        state.tp[0] = 12;
+        let best = plan_steps(&[heal], &state);
+        assert_eq!(best.score(), (20, 100));
+    }
+}
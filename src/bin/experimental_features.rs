@@ -21,9 +21,23 @@ mod prelude {
     pub use bevy_roguelike::resources::*;
     pub use bevy_roguelike::minimap::*;
     pub use bevy_roguelike::components::party::*;
+    pub use bevy_roguelike::components::character::*;
+    pub use bevy_roguelike::components::status::*;
+    pub use bevy_roguelike::scripting::*;
+    pub use bevy_roguelike::dialogue::*;
+    pub use bevy_roguelike::turn::*;
+    pub use bevy_roguelike::enemies::*;
+    pub use bevy_roguelike::encounters::*;
+    pub use bevy_roguelike::first_person::*;
+    pub use bevy_roguelike::combat::*;
+    pub use bevy_roguelike::skills::*;
+    pub use bevy_roguelike::items::*;
+    pub use bevy_roguelike::autoheal::*;
+    pub use bevy_roguelike::save::*;
+    pub use bevy_roguelike::textbox::*;
 }
 
-use bevy_roguelike::{components::party::party_movement_minimap, minimap};
+use bevy_roguelike::{components::party::{party_movement_minimap, update_facing_marker}, minimap};
 use prelude::*;
 
 
@@ -66,10 +80,15 @@ fn main_camera_setup(mut commands: Commands){
 
 
 
+// Places a test NPC next to the starting tile so the dialogue system has someone to talk to
+fn npc_setup(mut commands: Commands, mg: Res<MapGrid>){
+    spawn_npc(&mut commands, &mg, "Old Cartographer", "test_cartographer", Position{ x: 1, y: 0, z: 0 });
+}
+
 // Will need to use the MapBuildStates (Load/Render) specifically (Not yet implemented)
 fn main() {
-    App::new()
-    .add_plugins(DefaultPlugins
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins
         .set(WindowPlugin{
             primary_window: Some(Window{ 
                 title: "Rusty Odyssey".to_string(),
@@ -77,28 +96,95 @@ fn main() {
                 ..Default::default()
             }),
             ..Default::default()
-        }))
-    
+        }));
+    add_game(&mut app);
+    app.run();
+}
+
+// Everything except the window/render plugins - kept apart so the schedules can be built without a window in tests
+fn add_game(app: &mut App) {
+    app
     // Loads map rendering pipeline - might change state to reflect rendering process
     // TODO - bundle these up into a plugin? Should be doable in a sense.
     .add_state::<MapBuildState>()
     .add_state::<GameplayState>()
+    .add_state::<TurnState>()
+    .add_state::<CombatState>()
+    .add_state::<MenuState>()
+    .init_resource::<GameRng>()
+    .init_resource::<WorldState>()
+    .init_resource::<Explored>()
+    .init_resource::<Difficulty>()
+    .add_systems(Startup, (load_class_library, load_skill_library))
 
     // Load in the 2 cameras (1 for the game screen, 1 for the minimap, and 1 for the menu UI?)
     .add_systems(Startup, main_camera_setup) 
     .add_systems(Startup, minimap_camera_setup)
 
     .add_systems(OnEnter(MapBuildState::RenderMap), (despawn_system::<MapCellSprite>, despawn_system::<MapWallSprite>))
-    .add_systems(Update, (draw_grid, draw_wall, render_map).run_if(in_state(MapBuildState::RenderMap)))
+    // The real walls stay off the minimap when the player is drawing their own map
+    .add_systems(Update, (draw_grid, draw_wall.run_if(cartography_off), render_map).run_if(in_state(MapBuildState::RenderMap)))
+    .add_systems(Update, draw_map_icons)
     
     // The systems here should all share the commonality of "Window resize" or similar options
     .add_systems(Update, (minimap_camera_win_resize))
 
     // Loads in 'movable player' onto the map (Make use of the coordinate system), and sets up the 'exploring' state loop
-    .add_systems(OnEnter(GameplayState::Exploration), party_setup)
-    .add_systems(Update, party_movement_minimap.run_if(in_state(GameplayState::Exploration)))
+    // Exploration gets re-entered after every event/dialogue, so the party is only spawned the first time
+    .add_systems(OnEnter(GameplayState::Exploration), party_setup.run_if(run_once()))
+
+    // Turn loop - movement waits on input, tile checks run on the player's turn
+    .add_plugins((TurnPlugin, EnemyPlugin, EncounterPlugin))
+
+    // Combat - entered through TurnState::EnterCombat, then runs on CombatState until it hands back to exploring
+    .add_plugins((CombatPlugin, ItemPlugin, AutoHealPlugin))
+    .add_systems(Update, party_movement_minimap.in_set(TurnPhase::Input))
+    .add_systems(Update, update_facing_marker)
+
+    // Map transitions - stepping on a TransitionTile swaps maps through TurnState::EnterDungeon
+    .add_systems(Update, (check_transition_tiles, check_trap_tiles).in_set(TurnPhase::Player))
+
+    // HP changes go out as Damaged/Healed/Died events - breakables listen for their own deaths
+    // Status effects (Poison, buffs, etc...) tick on TurnPhase::Other while exploring
+    .add_plugins((HealthPlugin, StatusPlugin))
+    .add_systems(Update, strike_breakables.in_set(TurnPhase::Input))
+    .add_systems(Update, break_on_death)
+    // The map being left is snapshotted first so it comes back the way it was left, then an autosave is queued for the new one
+    .add_systems(OnEnter(TurnState::EnterDungeon), (queue_autosave, snapshot_current_map, enter_dungeon).chain())
+    .add_systems(Update, mark_explored)
+
+    // F5/F9 quicksave and quickload, Ctrl/Shift + 1-3 for the numbered slots, F6 lists them
+    .add_plugins(SavePlugin)
+
+    // Scripted events on EventTiles - these run through the shared text box
+    .add_plugins((TextBoxPlugin, ScriptingPlugin, DialoguePlugin))
+    .add_systems(OnEnter(GameplayState::Exploration), npc_setup.run_if(run_once()))
     .add_systems(Update, minimap_camera_style_toggle.run_if(in_state(GameplayState::Exploration)))
+    .add_systems(Update, map_metadata_on_load.run_if(resource_exists::<MapMetadata>()))
+
+    // First-person wireframe view on the main camera - V toggles it
+    .add_plugins(FirstPersonPlugin)
 
+    // Player-drawn map on the minimap - T to draw, saved with the game
+    .add_plugins(CartographyPlugin);
+}
 
-    .run();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ordering mistakes (Like ordering against a system added twice) only show up once a schedule is built,
+    // which would otherwise be the first frame of the game
+    #[test]
+    fn schedules_build() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins);
+        add_game(&mut app);
+        let mut schedules = app.world.remove_resource::<Schedules>().unwrap();
+        for (label, schedule) in schedules.iter_mut() {
+            if let Err(e) = schedule.initialize(&mut app.world) {
+                panic!("{:?} failed to build: {}", label, e);
+            }
+        }
+    }
 }
\ No newline at end of file
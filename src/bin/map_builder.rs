@@ -71,12 +71,17 @@ fn main() {
     .add_systems(OnEnter(MapBuildState::RenderMap), (despawn_system::<MapCellSprite>, despawn_system::<MapWallSprite>))
     .add_systems(Update, (draw_grid, draw_wall, render_map).run_if(in_state(MapBuildState::RenderMap)))
     .add_systems(Update, mouse_wall_gui.run_if(in_state(MapBuildState::Drawing)))
+
+    // Icon palette - 1-7 picks an icon, clicking the middle of a cell stamps it (Kept off while typing in the metadata panel)
+    .init_resource::<IconBrush>()
+    .add_systems(Update, mouse_icon_gui.run_if(in_state(MapBuildState::Drawing)).run_if(in_state(MBMenuState::Awaiting)))
+    .add_systems(Update, draw_map_icons)
     
     // Following system is just for the menu selections (Highlight, OnClick of valid menu slot) - will roll these into a plugin later
     .add_systems(Update, menu_button_system)
     .add_systems(Update, menu_action)
-    .add_systems(OnEnter(MBMenuState::Save), save_gui)
-    .add_systems(OnEnter(MBMenuState::Save), save_complete.after(save_gui))
+    // Save dialog and the metadata panel
+    .add_plugins(MenuPlugin)
 
     .run();
 }
\ No newline at end of file
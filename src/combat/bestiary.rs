@@ -0,0 +1,94 @@
+// Enemy stat blocks and the groups they show up in - loaded from assets/data/enemies.json
+// Encounter tables and foes refer to groups by id
+
+use std::collections::HashMap;
+use std::fs::File;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::combat::engine::*;
+use crate::components::status::StatusInflict;
+
+pub const ENEMY_FILE: &str = "assets/data/enemies.json";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EnemyDef {
+    pub id: String,
+    pub name: String,
+    pub hp: i32,
+    pub attack: i32,
+    pub defense: i32,
+    pub speed: i32,
+    #[serde(default)]
+    pub exp: u32,
+    #[serde(default)]
+    pub on_hit: Option<StatusInflict>,
+}
+
+impl EnemyDef {
+    pub fn to_combatant(&self, name: &str) -> Combatant {
+        let mut combatant = Combatant::new(name, Side::Enemy, self.hp, self.attack, self.defense, self.speed);
+        combatant.exp = self.exp;
+        combatant.on_hit = self.on_hit;
+        combatant
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EnemyGroup {
+    pub id: String,
+    pub enemies: Vec<String>,  // EnemyDef ids, repeats allowed
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct BestiaryFile {
+    enemies: Vec<EnemyDef>,
+    groups: Vec<EnemyGroup>,
+}
+
+#[derive(Resource, Default)]
+pub struct Bestiary {
+    pub enemies: HashMap<String, EnemyDef>,
+    pub groups: HashMap<String, EnemyGroup>,
+}
+
+impl Bestiary {
+    pub fn load_from_file(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Unable to open {}: {}", path, e))?;
+        let data: BestiaryFile = serde_json::from_reader(file)
+            .map_err(|e| format!("Unable to parse {}: {}", path, e))?;
+        Ok(Bestiary {
+            enemies: data.enemies.into_iter().map(|e| (e.id.clone(), e)).collect(),
+            groups: data.groups.into_iter().map(|g| (g.id.clone(), g)).collect(),
+        })
+    }
+
+    // Builds the enemy side of a battle - duplicates get lettered (Slime A, Slime B, ...)
+    pub fn build_group(&self, id: &str) -> Result<Vec<Combatant>, String> {
+        let group = self.groups.get(id).ok_or(format!("No enemy group named {}", id))?;
+        let mut combatants = Vec::new();
+        for enemy_id in &group.enemies {
+            let def = self.enemies.get(enemy_id).ok_or(format!("Group {} uses unknown enemy {}", id, enemy_id))?;
+            let count = group.enemies.iter().filter(|e| *e == enemy_id).count();
+            let name = if count > 1 {
+                let letter = (b'A' + combatants.iter().filter(|c: &&Combatant| c.name.starts_with(&def.name)).count() as u8) as char;
+                format!("{} {}", def.name, letter)
+            } else {
+                def.name.clone()
+            };
+            combatants.push(def.to_combatant(&name));
+        }
+        Ok(combatants)
+    }
+}
+
+pub fn load_bestiary(mut commands: Commands) {
+    match Bestiary::load_from_file(ENEMY_FILE) {
+        Ok(bestiary) => commands.insert_resource(bestiary),
+        Err(e) => {
+            println!("{} - battles will have no enemies", e);
+            commands.insert_resource(Bestiary::default());
+        }
+    }
+}
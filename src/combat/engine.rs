@@ -0,0 +1,556 @@
+// Core battle rules - plain Rust with no systems or queries, so it can be driven by the combat states or by hand
+// One round goes: everyone plans an action -> order is resolved by speed -> actions are applied in that order
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::{party::Row, status::*};
+use crate::items::*;
+use crate::resources::GameRng;
+use crate::skills::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    Player,
+    Enemy,
+}
+
+impl Side {
+    pub fn opponent(&self) -> Side {
+        match self {
+            Side::Player => Side::Enemy,
+            Side::Enemy => Side::Player,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Combatant {
+    pub name: String,
+    pub side: Side,
+    pub hp: i32,
+    pub max_hp: i32,
+    pub attack: i32,
+    pub defense: i32,
+    pub speed: i32,
+    #[serde(default)]
+    pub magic: i32,
+    #[serde(default)]
+    pub healing: i32,
+    #[serde(default)]
+    pub tp: i32,
+    #[serde(default)]
+    pub max_tp: i32,
+    #[serde(default)]
+    pub row: Row,          // Enemies all count as front row
+    #[serde(default)]
+    pub skills: BTreeMap<String, u32>,  // Skill id -> level
+    pub exp: u32,          // Awarded to the winners if this one goes down (Enemies only)
+    #[serde(skip)]
+    pub defending: bool,   // Only lasts for the round it was chosen in
+    #[serde(default)]
+    pub status: StatusEffects,
+    #[serde(default)]
+    pub on_hit: Option<StatusInflict>,  // Rolled every time one of its attacks connects
+}
+
+impl Combatant {
+    pub fn new(name: &str, side: Side, hp: i32, attack: i32, defense: i32, speed: i32) -> Self {
+        Combatant {
+            name: name.to_string(),
+            side,
+            hp,
+            max_hp: hp,
+            attack,
+            defense,
+            speed,
+            magic: 0,
+            healing: 0,
+            tp: 0,
+            max_tp: 0,
+            row: Row::Front,
+            skills: BTreeMap::new(),
+            exp: 0,
+            defending: false,
+            status: StatusEffects::default(),
+            on_hit: None,
+        }
+    }
+
+    // Stats after buffs/debuffs
+    pub fn effective_attack(&self) -> i32 {
+        self.status.modify(BuffStat::Attack, self.attack)
+    }
+
+    pub fn effective_defense(&self) -> i32 {
+        self.status.modify(BuffStat::Defense, self.defense)
+    }
+
+    pub fn effective_speed(&self) -> i32 {
+        self.status.modify(BuffStat::Speed, self.speed)
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.hp > 0
+    }
+
+    pub fn skill_level(&self, skill: &str) -> u32 {
+        self.skills.get(skill).copied().unwrap_or(0)
+    }
+
+    // Returns how much damage was actually taken
+    pub fn take_damage(&mut self, amount: i32) -> i32 {
+        let taken = amount.max(0).min(self.hp);
+        self.hp -= taken;
+        taken
+    }
+}
+
+// Targets are indices into Battle::combatants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    Attack { target: usize },
+    Skill { skill: String, target: usize },  // Target is ignored by skills that hit everyone/the user
+    Item { item: String, target: usize },
+    Defend,
+    Flee,
+    Wait,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BattleOutcome {
+    Victory,
+    Defeat,
+    Fled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Battle {
+    pub combatants: Vec<Combatant>,
+    pub planned: Vec<Option<Action>>,  // Indexed the same as combatants
+    pub order: Vec<usize>,             // Acting order for the current round, fastest first
+    pub outcome: Option<BattleOutcome>,
+    pub round: u32,
+    #[serde(default)]
+    pub inventory: Inventory,  // The player side's bag - items used in battle come out of here
+}
+
+const MIN_FLEE_CHANCE: i32 = 10;
+const MAX_FLEE_CHANCE: i32 = 90;
+
+impl Battle {
+    pub fn new(combatants: Vec<Combatant>) -> Self {
+        let planned = vec![None; combatants.len()];
+        let mut battle = Battle { combatants, planned, order: Vec::new(), outcome: None, round: 1, inventory: Inventory::default() };
+        battle.check_outcome();
+        battle
+    }
+
+    pub fn living(&self, side: Side) -> Vec<usize> {
+        self.combatants.iter().enumerate()
+            .filter(|(_, c)| c.side == side && c.is_alive())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    // Next living combatant on the side that still needs an action this round
+    pub fn next_unplanned(&self, side: Side) -> Option<usize> {
+        self.living(side).into_iter().find(|i| self.planned[*i].is_none())
+    }
+
+    pub fn plan(&mut self, actor: usize, action: Action) -> Result<(), String> {
+        let Some(combatant) = self.combatants.get(actor) else {
+            return Err(format!("No combatant at {}", actor));
+        };
+        if !combatant.is_alive() {
+            return Err(format!("{} can't act", combatant.name));
+        }
+        match &action {
+            Action::Attack { target } | Action::Skill { target, .. } | Action::Item { target, .. } if *target >= self.combatants.len() => {
+                return Err(format!("No combatant at {}", target));
+            }
+            Action::Skill { skill, .. } if combatant.skill_level(skill) == 0 => {
+                return Err(format!("{} doesn't know {}", combatant.name, skill));
+            }
+            Action::Item { item, .. } if !self.inventory.has(item) => {
+                return Err(format!("No {} left", item));
+            }
+            _ => {}
+        }
+        self.planned[actor] = Some(action);
+        Ok(())
+    }
+
+    // Basic enemy AI - swing at a random living member of the other side
+    pub fn plan_enemies(&mut self, rng: &mut GameRng) {
+        let targets = self.living(Side::Player);
+        if targets.is_empty() {
+            return;
+        }
+        while let Some(actor) = self.next_unplanned(Side::Enemy) {
+            let target = targets[rng.range(0, targets.len() as i32) as usize];
+            self.planned[actor] = Some(Action::Attack { target });
+        }
+    }
+
+    // Fastest acts first - a small random bonus keeps equal speeds from always going the same way
+    pub fn resolve_order(&mut self, rng: &mut GameRng) {
+        let mut order: Vec<(usize, i32)> = self.combatants.iter().enumerate()
+            .filter(|(_, c)| c.is_alive())
+            .map(|(i, c)| (i, c.effective_speed() + rng.range(0, 3)))
+            .collect();
+        order.sort_by(|a, b| b.1.cmp(&a.1));
+        self.order = order.into_iter().map(|(i, _)| i).collect();
+    }
+
+    // Applies the round's actions in order and returns a log of what happened
+    pub fn execute(&mut self, skills: &SkillLibrary, items: &ItemLibrary, rng: &mut GameRng) -> Vec<String> {
+        let mut log = Vec::new();
+
+        // Guarding takes effect straight away, not when the defender's turn comes up
+        for (i, action) in self.planned.iter().enumerate() {
+            if *action == Some(Action::Defend) {
+                self.combatants[i].defending = true;
+            }
+        }
+
+        for actor in self.order.clone() {
+            if self.outcome.is_some() {
+                break;
+            }
+            if !self.combatants[actor].is_alive() {
+                continue;
+            }
+            let action = self.planned[actor].clone().unwrap_or(Action::Wait);
+            let name = self.combatants[actor].name.clone();
+            match self.combatants[actor].status.incapacitated(rng) {
+                Some(Incapacitated::Asleep) => {
+                    log.push(format!("{} is fast asleep", name));
+                    continue;
+                }
+                Some(Incapacitated::Paralyzed) => {
+                    log.push(format!("{} is paralyzed and can't move!", name));
+                    continue;
+                }
+                None => {}
+            }
+            match action {
+                Action::Attack { target } => {
+                    let Some(target) = self.retarget(actor, target, rng) else { continue };
+                    let target_name = self.combatants[target].name.clone();
+                    if self.combatants[actor].status.misses(rng) {
+                        log.push(format!("{} swings blindly at {} and misses", name, target_name));
+                        continue;
+                    }
+                    let damage = damage_roll(&self.combatants[actor], &self.combatants[target], rng);
+                    let taken = self.combatants[target].take_damage(damage);
+                    log.push(format!("{} attacks {} for {} damage", name, target_name, taken));
+                    if !self.combatants[target].is_alive() {
+                        log.push(format!("{} is defeated!", target_name));
+                    } else {
+                        self.after_hit(actor, target, rng, &mut log);
+                    }
+                }
+                Action::Skill { skill, target } => {
+                    let Some(def) = skills.get(&skill) else {
+                        log.push(format!("{} fumbles around", name));
+                        continue;
+                    };
+                    // TP is only spent once the skill actually goes off
+                    if self.combatants[actor].tp < def.cost {
+                        log.push(format!("{} doesn't have enough TP for {}", name, def.name));
+                        continue;
+                    }
+                    self.combatants[actor].tp -= def.cost;
+                    log.push(format!("{} uses {}", name, def.name));
+                    let level = self.combatants[actor].skill_level(&skill);
+                    let user = SkillUser::from_combatant(&self.combatants[actor]);
+                    for hit in self.resolve_targets(actor, def.target, def.targets_dead(), target, rng) {
+                        log.extend(apply_effects(&def.effects, level, &user, &mut self.combatants[hit], rng));
+                    }
+                }
+                Action::Item { item, target } => {
+                    let Some(def) = items.get(&item) else {
+                        log.push(format!("{} fumbles around", name));
+                        continue;
+                    };
+                    // Two members can plan on the same last item - whoever goes second comes up empty
+                    if self.inventory.remove(&item, 1).is_err() {
+                        log.push(format!("{} reaches for a {}, but there are none left", name, def.name));
+                        continue;
+                    }
+                    log.push(format!("{} uses a {}", name, def.name));
+                    let user = item_user(def);
+                    for hit in self.resolve_targets(actor, def.target, def.targets_dead(), target, rng) {
+                        log.extend(apply_effects(&def.effects, 1, &user, &mut self.combatants[hit], rng));
+                    }
+                }
+                Action::Defend => log.push(format!("{} is on guard", name)),
+                Action::Flee => {
+                    if rng.roll(self.flee_chance()) {
+                        log.push("The party got away!".to_string());
+                        self.outcome = Some(BattleOutcome::Fled);
+                    } else {
+                        log.push(format!("{} couldn't get away!", name));
+                    }
+                }
+                Action::Wait => {}
+            }
+            self.check_outcome();
+        }
+
+        self.end_round(&mut log);
+        log
+    }
+
+    // Getting hit wakes a sleeper up, and the attacker might pass on a status
+    fn after_hit(&mut self, actor: usize, target: usize, rng: &mut GameRng, log: &mut Vec<String>) {
+        let target_name = self.combatants[target].name.clone();
+        if self.combatants[target].status.remove(StatusKind::Sleep) {
+            log.push(format!("{} wakes up!", target_name));
+        }
+        if let Some(inflict) = self.combatants[actor].on_hit {
+            if rng.roll(inflict.chance) && self.combatants[target].status.apply(inflict.effect) {
+                log.push(format!("{} is afflicted with {}!", target_name, inflict.effect.kind.label()));
+            }
+        }
+    }
+
+    // Works out who a skill or item actually hits - a dead single target gets swapped for another one on the same side
+    pub fn resolve_targets(&self, actor: usize, targeting: Targeting, targets_dead: bool, target: usize, rng: &mut GameRng) -> Vec<usize> {
+        let actor_side = self.combatants[actor].side;
+        let side = match targeting.side {
+            TargetSide::User => return vec![actor],
+            TargetSide::Ally => actor_side,
+            TargetSide::Enemy => actor_side.opponent(),
+        };
+        let dead_ok = targets_dead && side == actor_side;
+        let candidates: Vec<usize> = self.combatants.iter().enumerate()
+            .filter(|(_, c)| c.side == side && (c.is_alive() || dead_ok))
+            .map(|(i, _)| i)
+            .collect();
+        if candidates.is_empty() {
+            return candidates;
+        }
+        let chosen = if candidates.contains(&target) {
+            target
+        } else {
+            candidates[rng.range(0, candidates.len() as i32) as usize]
+        };
+        match targeting.scope {
+            TargetScope::Single => vec![chosen],
+            TargetScope::Row => {
+                let row = self.combatants[chosen].row;
+                candidates.into_iter().filter(|i| self.combatants[*i].row == row).collect()
+            }
+            TargetScope::All => candidates,
+        }
+    }
+
+    // Falls back to another living opponent if the planned target already went down
+    fn retarget(&self, actor: usize, target: usize, rng: &mut GameRng) -> Option<usize> {
+        if self.combatants[target].is_alive() {
+            return Some(target);
+        }
+        let options = self.living(self.combatants[actor].side.opponent());
+        if options.is_empty() {
+            return None;
+        }
+        Some(options[rng.range(0, options.len() as i32) as usize])
+    }
+
+    // Better odds the faster the party is compared to the enemies
+    pub fn flee_chance(&self) -> i32 {
+        let average_speed = |side: Side| {
+            let living = self.living(side);
+            if living.is_empty() {
+                return 0;
+            }
+            living.iter().map(|i| self.combatants[*i].effective_speed()).sum::<i32>() / living.len() as i32
+        };
+        (50 + (average_speed(Side::Player) - average_speed(Side::Enemy)) * 5).clamp(MIN_FLEE_CHANCE, MAX_FLEE_CHANCE)
+    }
+
+    pub fn check_outcome(&mut self) {
+        if self.outcome.is_some() {
+            return;
+        }
+        if self.living(Side::Enemy).is_empty() {
+            self.outcome = Some(BattleOutcome::Victory);
+        } else if self.living(Side::Player).is_empty() {
+            self.outcome = Some(BattleOutcome::Defeat);
+        }
+    }
+
+    // Statuses tick once everyone has acted - poison can still finish someone off here
+    fn end_round(&mut self, log: &mut Vec<String>) {
+        let over = self.outcome.is_some();
+        for combatant in self.combatants.iter_mut() {
+            combatant.defending = false;
+            if over || !combatant.is_alive() {
+                continue;
+            }
+            for tick in combatant.status.tick() {
+                match tick {
+                    StatusTick::Damage { kind, amount } => {
+                        let taken = combatant.take_damage(amount);
+                        log.push(format!("{} takes {} damage from {}", combatant.name, taken, kind.label()));
+                        if !combatant.is_alive() {
+                            log.push(format!("{} is defeated!", combatant.name));
+                        }
+                    }
+                    StatusTick::Expired(kind) => log.push(format!("{}'s {} wore off", combatant.name, kind.label())),
+                }
+            }
+        }
+        self.check_outcome();
+        self.planned = vec![None; self.combatants.len()];
+        self.order.clear();
+        self.round += 1;
+    }
+
+    // Exp from every defeated enemy
+    pub fn exp_reward(&self) -> u32 {
+        self.combatants.iter()
+            .filter(|c| c.side == Side::Enemy && !c.is_alive())
+            .map(|c| c.exp)
+            .sum()
+    }
+}
+
+// Attack minus half the defense (After buffs), +/- 10%, halved again if the target is guarding - always at least 1
+pub fn damage_roll(attacker: &Combatant, defender: &Combatant, rng: &mut GameRng) -> i32 {
+    let base = (attacker.effective_attack() - defender.effective_defense() / 2).max(1);
+    let mut damage = (base * rng.range(90, 111) / 100).max(1);
+    if defender.defending {
+        damage = (damage / 2).max(1);
+    }
+    damage
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hero(hp: i32, attack: i32, speed: i32) -> Combatant {
+        Combatant::new("Hero", Side::Player, hp, attack, 0, speed)
+    }
+
+    fn slime(hp: i32, attack: i32, speed: i32) -> Combatant {
+        let mut slime = Combatant::new("Slime", Side::Enemy, hp, attack, 0, speed);
+        slime.exp = 5;
+        slime
+    }
+
+    // Plans, orders and runs one round
+    fn round(battle: &mut Battle, actions: Vec<(usize, Action)>, rng: &mut GameRng) -> Vec<String> {
+        for (actor, action) in actions {
+            battle.plan(actor, action).unwrap();
+        }
+        battle.resolve_order(rng);
+        battle.execute(&SkillLibrary::default(), &ItemLibrary::default(), rng)
+    }
+
+    #[test]
+    fn fastest_acts_first() {
+        let mut rng = GameRng::new(7);
+        let mut battle = Battle::new(vec![hero(10, 1, 20), slime(10, 1, 50), slime(0, 1, 99), hero(10, 1, 5)]);
+        battle.resolve_order(&mut rng);
+        // The dead slime doesn't get a turn, and the random bonus is too small to swap these
+        assert_eq!(battle.order, vec![1, 0, 3]);
+    }
+
+    #[test]
+    fn faster_side_wins_the_race() {
+        let mut rng = GameRng::new(1);
+        let mut battle = Battle::new(vec![hero(5, 100, 1), slime(50, 100, 30)]);
+        let log = round(&mut battle, vec![(0, Action::Attack { target: 1 }), (1, Action::Attack { target: 0 })], &mut rng);
+        assert_eq!(battle.outcome, Some(BattleOutcome::Defeat));
+        assert_eq!(battle.combatants[1].hp, 50);
+        assert!(log.iter().all(|line| !line.starts_with("Hero attacks")));
+    }
+
+    #[test]
+    fn damage_is_attack_minus_half_defense() {
+        let mut rng = GameRng::new(3);
+        let attacker = hero(10, 20, 1);
+        let mut defender = slime(100, 1, 1);
+        defender.defense = 10;
+        for _ in 0..50 {
+            // 15 base, +/- 10%
+            assert!((13..=16).contains(&damage_roll(&attacker, &defender, &mut rng)));
+        }
+        defender.defense = 500;
+        assert_eq!(damage_roll(&attacker, &defender, &mut rng), 1);
+    }
+
+    #[test]
+    fn defending_halves_damage_for_the_round() {
+        let attacker = hero(10, 40, 1);
+        let mut defender = slime(100, 1, 1);
+        let mut rng = GameRng::new(11);
+        let normal = damage_roll(&attacker, &defender, &mut rng);
+        defender.defending = true;
+        let mut rng = GameRng::new(11);
+        assert_eq!(damage_roll(&attacker, &defender, &mut rng), normal / 2);
+
+        // Guarding counts even when the defender is slower than the attacker, and drops off after the round
+        let mut rng = GameRng::new(5);
+        let mut battle = Battle::new(vec![hero(10, 40, 50), slime(100, 1, 1)]);
+        round(&mut battle, vec![(0, Action::Attack { target: 1 }), (1, Action::Defend)], &mut rng);
+        assert!((18..=22).contains(&(100 - battle.combatants[1].hp)));
+        assert!(!battle.combatants[1].defending);
+    }
+
+    #[test]
+    fn flee_chance_follows_speed() {
+        assert_eq!(Battle::new(vec![hero(10, 1, 10), slime(10, 1, 10)]).flee_chance(), 50);
+        assert_eq!(Battle::new(vec![hero(10, 1, 14), slime(10, 1, 10)]).flee_chance(), 70);
+        assert_eq!(Battle::new(vec![hero(10, 1, 99), slime(10, 1, 1)]).flee_chance(), MAX_FLEE_CHANCE);
+        assert_eq!(Battle::new(vec![hero(10, 1, 1), slime(10, 1, 99)]).flee_chance(), MIN_FLEE_CHANCE);
+    }
+
+    #[test]
+    fn flee_succeeds_and_fails_with_the_roll() {
+        let (mut fled, mut stayed) = (false, false);
+        for seed in 1..100 {
+            let mut rng = GameRng::new(seed);
+            let mut battle = Battle::new(vec![hero(10, 1, 50), slime(10, 1, 1)]);
+            battle.plan(0, Action::Flee).unwrap();
+            battle.plan(1, Action::Wait).unwrap();
+            battle.resolve_order(&mut rng);
+            // Nothing else rolls before the flee attempt, so a copy of the RNG knows how it'll go
+            let escapes = rng.clone().roll(battle.flee_chance());
+            let log = battle.execute(&SkillLibrary::default(), &ItemLibrary::default(), &mut rng);
+            if escapes {
+                assert_eq!(battle.outcome, Some(BattleOutcome::Fled));
+                fled = true;
+            } else {
+                assert_eq!(battle.outcome, None);
+                assert!(log.contains(&"Hero couldn't get away!".to_string()));
+                stayed = true;
+            }
+        }
+        assert!(fled && stayed);
+    }
+
+    #[test]
+    fn victory_when_every_enemy_is_down() {
+        let mut rng = GameRng::new(9);
+        let mut battle = Battle::new(vec![hero(10, 100, 50), slime(5, 1, 1), slime(5, 1, 1)]);
+        round(&mut battle, vec![(0, Action::Attack { target: 1 })], &mut rng);
+        assert_eq!(battle.outcome, None);
+        assert_eq!(battle.round, 2);
+        // Planned target is already down, so the attack moves on to the other slime
+        round(&mut battle, vec![(0, Action::Attack { target: 1 })], &mut rng);
+        assert_eq!(battle.outcome, Some(BattleOutcome::Victory));
+        assert_eq!(battle.exp_reward(), 10);
+    }
+
+    #[test]
+    fn outcome_checked_on_creation() {
+        assert_eq!(Battle::new(vec![hero(10, 1, 1), slime(0, 1, 1)]).outcome, Some(BattleOutcome::Victory));
+        assert_eq!(Battle::new(vec![hero(0, 1, 1), slime(10, 1, 1)]).outcome, Some(BattleOutcome::Defeat));
+    }
+}
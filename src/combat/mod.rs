@@ -0,0 +1,445 @@
+// Combat - runs on CombatState once the turn loop hits TurnState::EnterCombat
+// EnteredCombat -> Planning (Player picks actions) -> Computing (Speed order) -> Executing (Actions play out)
+//      -> back to Planning, or ExitingCombat on victory/defeat/fleeing
+// The rules themselves live in engine.rs - this file is just the Bevy side (States, text box menus, party sync)
+
+pub mod bestiary;
+pub mod engine;
+
+pub use bestiary::*;
+pub use engine::*;
+
+use bevy::prelude::*;
+
+use crate::components::{character::*, party::{Party, PartyMember, PlayerParty}, HealthEvents, Position};
+use crate::encounters::EncounterTables;
+use crate::items::*;
+use crate::minimap::*;
+use crate::resources::*;
+use crate::skills::*;
+use crate::textbox::*;
+
+// Battle in progress, plus where the planning menu is at
+#[derive(Resource, Debug, Clone)]
+pub struct ActiveBattle {
+    pub battle: Battle,
+    pub foe: Option<Entity>,          // On-map enemy to remove if we win
+    pub members: Vec<usize>,          // Party slot for each player-side combatant, in combatant order
+    pub menu: PlanMenu,
+}
+
+// Which planning menu the current actor is looking at
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum PlanMenu {
+    #[default]
+    Root,
+    Skills,
+    Items,
+    Target(PlanChoice),  // Picking who to hit
+}
+
+// What the target being picked is for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanChoice {
+    Attack,
+    Skill(String),
+    Item(String),
+}
+
+impl PlanChoice {
+    fn into_action(self, target: usize) -> Action {
+        match self {
+            PlanChoice::Attack => Action::Attack { target },
+            PlanChoice::Skill(skill) => Action::Skill { skill, target },
+            PlanChoice::Item(item) => Action::Item { item, target },
+        }
+    }
+}
+
+// Sent once a battle wraps up
+#[derive(Event, Debug, Clone)]
+pub struct CombatEnded {
+    pub outcome: BattleOutcome,
+    pub exp: u32,
+}
+
+const PLANNING_OPTIONS: [&str; 5] = ["Attack", "Skill", "Item", "Defend", "Flee"];
+
+pub fn character_combatant(member: &PartyMember) -> Combatant {
+    let character = &member.character;
+    let mut combatant = Combatant::new(&character.name, Side::Player, character.health.hp(), character.attack(), character.defense(), character.speed());
+    combatant.max_hp = character.health.max_hp();
+    combatant.magic = character.magic();
+    combatant.healing = character.healing();
+    combatant.tp = character.tp;
+    combatant.max_tp = character.max_tp;
+    combatant.row = member.row;
+    combatant.skills = character.skills.clone();
+    combatant.status = character.status.clone();
+    combatant
+}
+
+// Skills the actor can pick from in battle, in the order the menu lists them
+fn combat_skills<'a>(battle: &Battle, actor: usize, skills: &'a SkillLibrary) -> Vec<&'a SkillDef> {
+    battle.combatants[actor].skills.keys()
+        .filter_map(|id| skills.get(id))
+        .filter(|s| s.usable.in_combat())
+        .collect()
+}
+
+// Items still in the bag once everyone already planning to use one has taken theirs
+fn combat_items<'a>(battle: &Battle, items: &'a ItemLibrary) -> Vec<(&'a ItemDef, u32)> {
+    battle.inventory.usable(items, true).into_iter()
+        .map(|(def, qty)| {
+            let reserved = battle.planned.iter()
+                .filter(|a| matches!(a, Some(Action::Item { item, .. }) if *item == def.id))
+                .count() as u32;
+            (def, qty.saturating_sub(reserved))
+        })
+        .filter(|(_, qty)| *qty > 0)
+        .collect()
+}
+
+// Who shows up in the target list - enemies for attacks, otherwise whichever side the skill/item aims at
+fn target_choices(battle: &Battle, actor: usize, choice: &PlanChoice, skills: &SkillLibrary, items: &ItemLibrary) -> Vec<usize> {
+    let side = battle.combatants[actor].side;
+    let (targeting, targets_dead) = match choice {
+        PlanChoice::Attack => return battle.living(side.opponent()),
+        PlanChoice::Skill(id) => match skills.get(id) {
+            Some(skill) => (skill.target, skill.targets_dead()),
+            None => return Vec::new(),
+        },
+        PlanChoice::Item(id) => match items.get(id) {
+            Some(item) => (item.target, item.targets_dead()),
+            None => return Vec::new(),
+        },
+    };
+    if targeting.side == TargetSide::Enemy {
+        return battle.living(side.opponent());
+    }
+    battle.combatants.iter().enumerate()
+        .filter(|(_, c)| c.side == side && (c.is_alive() || targets_dead))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+fn choice_name(choice: &PlanChoice, skills: &SkillLibrary, items: &ItemLibrary) -> String {
+    match choice {
+        PlanChoice::Attack => "Attack".to_string(),
+        PlanChoice::Skill(id) => skills.get(id).map(|s| s.name.clone()).unwrap_or(id.clone()),
+        PlanChoice::Item(id) => format!("Use {} on", items.name(id)),
+    }
+}
+
+// OnEnter(TurnState::EnterCombat) - sets up the battle from whatever queued it
+pub fn begin_combat(
+    mut commands: Commands,
+    pending: Option<Res<PendingBattle>>,
+    party: Query<(&Party, &Position, Option<&Inventory>), With<PlayerParty>>,
+    bestiary: Res<Bestiary>,
+    tables: Res<EncounterTables>,
+    mg: Res<MapGrid>,
+    meta: Option<Res<MapMetadata>>,
+    mut rng: ResMut<GameRng>,
+    mut next_turn: ResMut<NextState<TurnState>>,
+    mut next_combat: ResMut<NextState<CombatState>>,
+) {
+    commands.remove_resource::<PendingBattle>();
+    let pending = pending.map(|p| p.clone()).unwrap_or_default();
+    let Ok((party, pos, inventory)) = party.get_single() else { return };
+
+    // Nothing specific was asked for - fight whatever lives here
+    let encounter = pending.encounter
+        .or_else(|| tables.table_at(&mg, meta.as_deref(), pos).and_then(|t| t.pick_group(&mut rng)));
+    let enemies = match encounter {
+        Some(id) => bestiary.build_group(&id),
+        None => Err("No encounter to fight".to_string()),
+    };
+    let mut enemies = match enemies {
+        Ok(enemies) if !enemies.is_empty() => enemies,
+        Ok(_) => {
+            println!("Enemy group is empty - skipping combat");
+            next_turn.set(TurnState::AwaitingInput);
+            return;
+        }
+        Err(e) => {
+            println!("{} - skipping combat", e);
+            next_turn.set(TurnState::AwaitingInput);
+            return;
+        }
+    };
+
+    // Downed members sit the fight out
+    let members: Vec<usize> = party.members.iter().enumerate()
+        .filter(|(_, m)| m.character.is_alive())
+        .map(|(i, _)| i)
+        .collect();
+    let mut combatants: Vec<Combatant> = members.iter().map(|i| character_combatant(&party.members[*i])).collect();
+    combatants.append(&mut enemies);
+    let names: Vec<String> = combatants.iter().filter(|c| c.side == Side::Enemy).map(|c| c.name.clone()).collect();
+    println!("Battle start! {}", names.join(", "));
+
+    let mut battle = Battle::new(combatants);
+    battle.inventory = inventory.cloned().unwrap_or_default();
+    commands.insert_resource(ActiveBattle { battle, foe: pending.foe, members, menu: PlanMenu::Root });
+    next_combat.set(CombatState::Planning);
+}
+
+// Puts up the menu for whoever is planning next
+fn prompt_planning(commands: &mut Commands, active: &ActiveBattle, skills: &SkillLibrary, items: &ItemLibrary) {
+    let battle = &active.battle;
+    let Some(actor) = battle.next_unplanned(Side::Player) else { return };
+    let combatant = &battle.combatants[actor];
+    let back = "Back".to_string();
+    match &active.menu {
+        PlanMenu::Root => {
+            let text = format!("Round {} - {} HP {}/{} TP {}/{}. What will they do?",
+                battle.round, combatant.name, combatant.hp, combatant.max_hp, combatant.tp, combatant.max_tp);
+            let options: Vec<String> = PLANNING_OPTIONS.iter().map(|o| o.to_string()).collect();
+            spawn_textbox(commands, Some(&combatant.name), &text, &options);
+        }
+        PlanMenu::Skills => {
+            let mut options: Vec<String> = combat_skills(battle, actor, skills).iter()
+                .map(|s| format!("{} Lv{} ({} TP)", s.name, combatant.skill_level(&s.id), s.cost))
+                .collect();
+            options.push(back);
+            spawn_textbox(commands, Some(&combatant.name), &format!("Which skill? ({} TP)", combatant.tp), &options);
+        }
+        PlanMenu::Items => {
+            let mut options: Vec<String> = combat_items(battle, items).iter()
+                .map(|(item, qty)| format!("{} x{}", item.name, qty))
+                .collect();
+            options.push(back);
+            spawn_textbox(commands, Some(&combatant.name), "Which item?", &options);
+        }
+        PlanMenu::Target(choice) => {
+            let mut options: Vec<String> = target_choices(battle, actor, choice, skills, items).iter()
+                .map(|i| format!("{} ({} HP)", battle.combatants[*i].name, battle.combatants[*i].hp))
+                .collect();
+            options.push(back);
+            let text = format!("{} who?", choice_name(choice, skills, items));
+            spawn_textbox(commands, Some(&combatant.name), &text, &options);
+        }
+    }
+}
+
+pub fn enter_planning(mut commands: Commands, active: Res<ActiveBattle>, skills: Res<SkillLibrary>, items: Res<ItemLibrary>) {
+    prompt_planning(&mut commands, &active, &skills, &items);
+}
+
+// CombatState::Planning - turns menu picks into planned actions
+pub fn combat_planning(
+    mut commands: Commands,
+    mut active: ResMut<ActiveBattle>,
+    skills: Res<SkillLibrary>,
+    items: Res<ItemLibrary>,
+    mut inputs: EventReader<TextBoxInput>,
+    boxes: Query<Entity, With<TextBox>>,
+    mut rng: ResMut<GameRng>,
+    mut next_combat: ResMut<NextState<CombatState>>,
+) {
+    let mut changed = false;
+    for input in inputs.read() {
+        let TextBoxInput::Choose(i) = input else { continue };
+        let Some(actor) = active.battle.next_unplanned(Side::Player) else { break };
+
+        let mut action = None;
+        match active.menu.clone() {
+            PlanMenu::Root => match *i {
+                0 => active.menu = PlanMenu::Target(PlanChoice::Attack),
+                1 => active.menu = PlanMenu::Skills,
+                2 => active.menu = PlanMenu::Items,
+                3 => action = Some(Action::Defend),
+                4 => action = Some(Action::Flee),
+                _ => continue,
+            },
+            PlanMenu::Skills => {
+                let known = combat_skills(&active.battle, actor, &skills);
+                match known.get(*i) {
+                    Some(skill) if active.battle.combatants[actor].tp < skill.cost => {
+                        println!("{} doesn't have enough TP for {}", active.battle.combatants[actor].name, skill.name);
+                        continue;
+                    }
+                    // Skills that hit everyone (Or just the user) go straight in
+                    Some(skill) if !skill.target.needs_target() => {
+                        action = Some(Action::Skill { skill: skill.id.clone(), target: actor });
+                    }
+                    Some(skill) => active.menu = PlanMenu::Target(PlanChoice::Skill(skill.id.clone())),
+                    // Past the last skill is the Back option
+                    None if *i == known.len() => active.menu = PlanMenu::Root,
+                    None => continue,
+                }
+            }
+            PlanMenu::Items => {
+                let usable = combat_items(&active.battle, &items);
+                match usable.get(*i) {
+                    Some((item, _)) if !item.target.needs_target() => {
+                        action = Some(Action::Item { item: item.id.clone(), target: actor });
+                    }
+                    Some((item, _)) => active.menu = PlanMenu::Target(PlanChoice::Item(item.id.clone())),
+                    None if *i == usable.len() => active.menu = PlanMenu::Root,
+                    None => continue,
+                }
+            }
+            PlanMenu::Target(choice) => {
+                let targets = target_choices(&active.battle, actor, &choice, &skills, &items);
+                match targets.get(*i) {
+                    Some(target) => action = Some(choice.into_action(*target)),
+                    None if *i == targets.len() => {
+                        active.menu = match choice {
+                            PlanChoice::Attack => PlanMenu::Root,
+                            PlanChoice::Skill(_) => PlanMenu::Skills,
+                            PlanChoice::Item(_) => PlanMenu::Items,
+                        };
+                    }
+                    None => continue,
+                }
+            }
+        }
+        if let Some(action) = action {
+            if let Err(e) = active.battle.plan(actor, action) {
+                println!("{}", e);
+            }
+            active.menu = PlanMenu::Root;
+        }
+        changed = true;
+        break;
+    }
+    if !changed {
+        return;
+    }
+
+    despawn_textbox(&mut commands, &boxes);
+    if active.menu == PlanMenu::Root && active.battle.next_unplanned(Side::Player).is_none() {
+        active.battle.plan_enemies(&mut rng);
+        next_combat.set(CombatState::Computing);
+    } else {
+        prompt_planning(&mut commands, &active, &skills, &items);
+    }
+}
+
+// OnEnter(CombatState::Computing)
+pub fn compute_turn_order(
+    mut active: ResMut<ActiveBattle>,
+    mut rng: ResMut<GameRng>,
+    mut next_combat: ResMut<NextState<CombatState>>,
+) {
+    active.battle.resolve_order(&mut rng);
+    next_combat.set(CombatState::Executing);
+}
+
+// OnEnter(CombatState::Executing) - plays the round out and shows what happened
+pub fn execute_round(
+    mut commands: Commands,
+    mut active: ResMut<ActiveBattle>,
+    skills: Res<SkillLibrary>,
+    items: Res<ItemLibrary>,
+    mut rng: ResMut<GameRng>,
+) {
+    let log = active.battle.execute(&skills, &items, &mut rng);
+    let text = if log.is_empty() { "Nothing happens...".to_string() } else { log.join("\n") };
+    spawn_textbox(&mut commands, None, &text, &[]);
+}
+
+// CombatState::Executing - waits for the player to read the round's log
+pub fn combat_executing(
+    mut commands: Commands,
+    active: Res<ActiveBattle>,
+    mut inputs: EventReader<TextBoxInput>,
+    boxes: Query<Entity, With<TextBox>>,
+    mut next_combat: ResMut<NextState<CombatState>>,
+) {
+    if !inputs.read().any(|input| *input == TextBoxInput::Confirm) {
+        return;
+    }
+    despawn_textbox(&mut commands, &boxes);
+    if active.battle.outcome.is_some() {
+        next_combat.set(CombatState::ExitingCombat);
+    } else {
+        next_combat.set(CombatState::Planning);
+    }
+}
+
+// OnEnter(CombatState::ExitingCombat) - writes the results back to the world and resumes exploring
+pub fn end_combat(
+    mut commands: Commands,
+    active: Res<ActiveBattle>,
+    mut party: Query<(Entity, &mut Party, Option<&mut Inventory>), With<PlayerParty>>,
+    classes: Res<ClassLibrary>,
+    mut health_events: HealthEvents,
+    mut ended: EventWriter<CombatEnded>,
+    mut next_turn: ResMut<NextState<TurnState>>,
+    mut next_combat: ResMut<NextState<CombatState>>,
+) {
+    let battle = &active.battle;
+    let outcome = battle.outcome.unwrap_or(BattleOutcome::Fled);
+
+    let exp = battle.exp_reward();
+    println!("Battle over - {:?} ({} exp)", outcome, exp);
+
+    if let Ok((entity, mut party, inventory)) = party.get_single_mut() {
+        // Whatever got used up in the fight is gone for good
+        if let Some(mut inventory) = inventory {
+            *inventory = battle.inventory.clone();
+        }
+        let player_side = battle.combatants.iter().filter(|c| c.side == Side::Player);
+        for (slot, combatant) in active.members.iter().zip(player_side) {
+            let Some(member) = party.members.get_mut(*slot) else { continue };
+            health_events.set_hp(&mut member.character.health, combatant.hp, entity, Some(*slot));
+            member.character.tp = combatant.tp;
+            member.character.status = combatant.status.clone();
+            member.character.status.end_combat();
+        }
+        // TODO - game over screen, for now a wiped party limps away with the leader on 1 HP
+        if outcome == BattleOutcome::Defeat {
+            if let Some(leader) = party.leader_index() {
+                party.members[leader].character.health.revive(1);
+            }
+        }
+        // Everyone still standing gets the full amount
+        if outcome == BattleOutcome::Victory {
+            for member in party.members.iter_mut().filter(|m| m.character.is_alive()) {
+                let Some(class) = classes.get(&member.character.class) else { continue };
+                let levels = member.character.gain_exp(exp, class);
+                if levels > 0 {
+                    println!("{} reached level {}!", member.character.name, member.character.level);
+                }
+            }
+        }
+    }
+    if outcome == BattleOutcome::Victory {
+        if let Some(foe) = active.foe {
+            commands.entity(foe).despawn_recursive();
+        }
+    }
+
+    ended.send(CombatEnded { outcome, exp });
+
+    commands.remove_resource::<ActiveBattle>();
+    // Reset so the next battle goes through EnteredCombat again
+    next_combat.set(CombatState::EnteredCombat);
+    next_turn.set(TurnState::AwaitingInput);
+}
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<CombatEnded>()
+            .add_systems(Startup, load_bestiary)
+            .add_systems(OnEnter(TurnState::EnterCombat), begin_combat)
+            .add_systems(OnEnter(CombatState::Planning), enter_planning)
+            .add_systems(OnEnter(CombatState::Computing), compute_turn_order)
+            .add_systems(OnEnter(CombatState::Executing), execute_round)
+            .add_systems(OnEnter(CombatState::ExitingCombat), end_combat)
+            .add_systems(Update, combat_planning
+                .after(textbox_input)
+                .run_if(in_state(CombatState::Planning))
+                .run_if(resource_exists::<ActiveBattle>()))
+            .add_systems(Update, combat_executing
+                .after(textbox_input)
+                .run_if(in_state(CombatState::Executing))
+                .run_if(resource_exists::<ActiveBattle>()));
+    }
+}
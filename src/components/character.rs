@@ -0,0 +1,248 @@
+// Characters that make up a party - base stats, a class, and experience/levels
+// Classes are data-driven (assets/data/classes.json) so balance tweaks don't need a rebuild
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::ops::{Add, AddAssign};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{equipment::Equipment, status::StatusEffects, Health};
+use crate::skills::SkillDef;
+
+pub const CLASS_FILE: &str = "assets/data/classes.json";
+pub const LEVEL_CAP: u32 = 99;
+pub const STARTING_SKILL_POINTS: u32 = 3;
+pub const SKILL_POINTS_PER_LEVEL: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct Stats {
+    pub strength: i32,   // Physical attack
+    pub vitality: i32,   // Max HP and physical defense
+    pub agility: i32,    // Turn order, accuracy, evasion
+    pub intellect: i32,  // Magic attack
+    pub wisdom: i32,     // Magic defense and healing
+    pub luck: i32,       // A little bit of everything (Crits, status, fleeing)
+}
+
+impl Add for Stats {
+    type Output = Stats;
+
+    fn add(self, other: Stats) -> Stats {
+        Stats {
+            strength: self.strength + other.strength,
+            vitality: self.vitality + other.vitality,
+            agility: self.agility + other.agility,
+            intellect: self.intellect + other.intellect,
+            wisdom: self.wisdom + other.wisdom,
+            luck: self.luck + other.luck,
+        }
+    }
+}
+
+impl AddAssign for Stats {
+    fn add_assign(&mut self, other: Stats) {
+        *self = *self + other;
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ClassDef {
+    pub id: String,
+    pub name: String,
+    pub base_hp: i32,
+    #[serde(default)]
+    pub hp_growth: i32,  // Extra max HP per level, on top of what vitality gives
+    pub base: Stats,     // Stats at level 1
+    #[serde(default)]
+    pub growth: Stats,   // Added on every level up
+    #[serde(default)]
+    pub base_tp: i32,
+    #[serde(default)]
+    pub tp_growth: i32,
+    #[serde(default)]
+    pub skills: Vec<String>,  // The class's skill tree - prerequisites live on the skills themselves
+}
+
+impl ClassDef {
+    // Max HP for a character of this class - vitality is counted separately since equipment/effects can change it
+    pub fn max_hp(&self, level: u32, stats: &Stats) -> i32 {
+        (self.base_hp + self.hp_growth * (level as i32 - 1) + stats.vitality * 2).max(1)
+    }
+
+    pub fn max_tp(&self, level: u32, stats: &Stats) -> i32 {
+        (self.base_tp + self.tp_growth * (level as i32 - 1) + stats.wisdom).max(0)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ClassLibrary {
+    pub classes: HashMap<String, ClassDef>,
+}
+
+impl ClassLibrary {
+    pub fn load_from_file(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Unable to open {}: {}", path, e))?;
+        let classes: Vec<ClassDef> = serde_json::from_reader(file)
+            .map_err(|e| format!("Unable to parse {}: {}", path, e))?;
+        Ok(ClassLibrary { classes: classes.into_iter().map(|c| (c.id.clone(), c)).collect() })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ClassDef> {
+        self.classes.get(id)
+    }
+}
+
+pub fn load_class_library(mut commands: Commands) {
+    match ClassLibrary::load_from_file(CLASS_FILE) {
+        Ok(lib) => commands.insert_resource(lib),
+        Err(e) => {
+            println!("{} - no classes available", e);
+            commands.insert_resource(ClassLibrary::default());
+        }
+    }
+}
+
+// Exp needed to go from `level` to the next one
+pub fn exp_to_next(level: u32) -> u32 {
+    10 * level * level + 10 * level
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Character {
+    pub name: String,
+    pub class: String,  // Id of the ClassDef
+    pub level: u32,
+    pub exp: u32,       // Exp towards the next level (Resets on level up)
+    pub stats: Stats,
+    pub health: Health,
+    #[serde(default)]
+    pub status: StatusEffects,
+    #[serde(default)]
+    pub tp: i32,
+    #[serde(default)]
+    pub max_tp: i32,
+    #[serde(default)]
+    pub skills: BTreeMap<String, u32>,  // Learned skill id -> level
+    #[serde(default)]
+    pub skill_points: u32,
+    #[serde(default)]
+    pub equipment: Equipment,
+}
+
+impl Character {
+    // Fresh level 1 character at full HP
+    pub fn new(name: &str, class: &ClassDef) -> Character {
+        let max_hp = class.max_hp(1, &class.base);
+        let max_tp = class.max_tp(1, &class.base);
+        Character {
+            name: name.to_string(),
+            class: class.id.clone(),
+            level: 1,
+            exp: 0,
+            stats: class.base,
+            health: Health::new(max_hp),
+            status: StatusEffects::default(),
+            tp: max_tp,
+            max_tp,
+            skills: BTreeMap::new(),
+            skill_points: STARTING_SKILL_POINTS,
+            equipment: Equipment::default(),
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        !self.health.is_dead()
+    }
+
+    // Adds exp and applies any level ups it causes - returns how many levels were gained
+    pub fn gain_exp(&mut self, amount: u32, class: &ClassDef) -> u32 {
+        if self.level >= LEVEL_CAP {
+            return 0;
+        }
+        self.exp += amount;
+        let mut gained = 0;
+        while self.level < LEVEL_CAP && self.exp >= exp_to_next(self.level) {
+            self.exp -= exp_to_next(self.level);
+            self.level_up(class);
+            gained += 1;
+        }
+        if self.level >= LEVEL_CAP {
+            self.exp = 0;
+        }
+        gained
+    }
+
+    // Growth is fixed per class for now - the max HP gained is also restored
+    fn level_up(&mut self, class: &ClassDef) {
+        self.level += 1;
+        self.stats += class.growth;
+        let new_max = class.max_hp(self.level, &self.stats);
+        let gained = new_max - self.health.max_hp();
+        self.health.set_max_hp(new_max);
+        self.health.heal(gained);
+
+        let new_max_tp = class.max_tp(self.level, &self.stats);
+        self.tp = (self.tp + new_max_tp - self.max_tp).clamp(0, new_max_tp);
+        self.max_tp = new_max_tp;
+        self.skill_points += SKILL_POINTS_PER_LEVEL;
+    }
+
+    pub fn skill_level(&self, skill: &str) -> u32 {
+        self.skills.get(skill).copied().unwrap_or(0)
+    }
+
+    // Spends a point to learn a skill (Or raise it a level) - it has to be in the class's tree with its prerequisites met
+    pub fn learn_skill(&mut self, skill: &SkillDef, class: &ClassDef) -> Result<u32, String> {
+        if !class.skills.contains(&skill.id) {
+            return Err(format!("{}s can't learn {}", class.name, skill.name));
+        }
+        if self.skill_points == 0 {
+            return Err(format!("{} has no skill points left", self.name));
+        }
+        let level = self.skill_level(&skill.id);
+        if level >= skill.max_level {
+            return Err(format!("{} is already at max level", skill.name));
+        }
+        if let Some(missing) = skill.requires.iter().find(|r| self.skill_level(&r.skill) < r.level) {
+            return Err(format!("{} needs {} at level {} first", skill.name, missing.skill, missing.level));
+        }
+        self.skill_points -= 1;
+        self.skills.insert(skill.id.clone(), level + 1);
+        Ok(level + 1)
+    }
+
+    pub fn restore_tp(&mut self, amount: i32) -> i32 {
+        let restored = amount.max(0).min(self.max_tp - self.tp);
+        self.tp += restored;
+        restored
+    }
+
+    // Base stats plus whatever the equipped gear adds
+    pub fn total_stats(&self) -> Stats {
+        self.stats + self.equipment.bonus().stats
+    }
+
+    // Derived combat numbers, gear included
+    pub fn attack(&self) -> i32 {
+        self.total_stats().strength + self.level as i32 + self.equipment.bonus().attack
+    }
+
+    pub fn defense(&self) -> i32 {
+        self.total_stats().vitality + self.equipment.bonus().defense
+    }
+
+    pub fn speed(&self) -> i32 {
+        self.total_stats().agility + self.equipment.bonus().speed
+    }
+
+    pub fn magic(&self) -> i32 {
+        self.total_stats().intellect + self.level as i32 + self.equipment.bonus().magic
+    }
+
+    pub fn healing(&self) -> i32 {
+        self.total_stats().wisdom + self.level as i32 + self.equipment.bonus().healing
+    }
+}
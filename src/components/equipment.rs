@@ -0,0 +1,183 @@
+// Equipment - one weapon, armor and accessory per character
+// Gear is just an item (kind Equipment) with an `equip` block saying which slot it goes in, who can use it and what it adds
+// The modifiers are copied onto the character when equipped, so the derived combat numbers don't need the item library
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::character::*;
+use crate::items::*;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EquipSlot {
+    Weapon,
+    Armor,
+    Accessory,
+}
+
+pub const EQUIP_SLOTS: [EquipSlot; 3] = [EquipSlot::Weapon, EquipSlot::Armor, EquipSlot::Accessory];
+
+// Bonuses from a piece of gear - stats feed into the derived numbers, the flat values go straight on top
+// Max HP/TP still only come from base stats, so swapping gear never changes them
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct EquipMods {
+    pub stats: Stats,
+    pub attack: i32,
+    pub defense: i32,
+    pub speed: i32,
+    pub magic: i32,
+    pub healing: i32,
+}
+
+impl EquipMods {
+    fn total<'a>(mods: impl Iterator<Item = &'a EquipMods>) -> EquipMods {
+        mods.fold(EquipMods::default(), |acc, m| EquipMods {
+            stats: acc.stats + m.stats,
+            attack: acc.attack + m.attack,
+            defense: acc.defense + m.defense,
+            speed: acc.speed + m.speed,
+            magic: acc.magic + m.magic,
+            healing: acc.healing + m.healing,
+        })
+    }
+}
+
+// The `equip` block on an ItemDef
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EquipInfo {
+    pub slot: EquipSlot,
+    #[serde(default)]
+    pub classes: Vec<String>,  // Class ids allowed to use it - empty means anyone
+    #[serde(default)]
+    pub mods: EquipMods,
+}
+
+impl EquipInfo {
+    pub fn allows(&self, class: &str) -> bool {
+        self.classes.is_empty() || self.classes.iter().any(|c| c == class)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Equipped {
+    pub item: String,
+    pub mods: EquipMods,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Equipment {
+    pub slots: BTreeMap<EquipSlot, Equipped>,
+}
+
+impl Equipment {
+    pub fn get(&self, slot: EquipSlot) -> Option<&Equipped> {
+        self.slots.get(&slot)
+    }
+
+    pub fn bonus(&self) -> EquipMods {
+        EquipMods::total(self.slots.values().map(|e| &e.mods))
+    }
+}
+
+// Derived numbers before and after a swap - what an equip screen shows next to each stat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatChange {
+    pub before: i32,
+    pub after: i32,
+}
+
+impl StatChange {
+    pub fn diff(&self) -> i32 {
+        self.after - self.before
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EquipCompare {
+    pub slot: EquipSlot,
+    pub current: Option<String>,  // Item that would be swapped out
+    pub attack: StatChange,
+    pub defense: StatChange,
+    pub speed: StatChange,
+    pub magic: StatChange,
+    pub healing: StatChange,
+}
+
+// Checks the item is gear this class can wear, and returns where it goes
+pub fn can_equip<'a>(item: &'a ItemDef, class: &ClassDef) -> Result<&'a EquipInfo, String> {
+    let info = match (&item.kind, &item.equip) {
+        (ItemKind::Equipment, Some(info)) => info,
+        _ => return Err(format!("{} isn't something you can equip", item.name)),
+    };
+    if !info.allows(&class.id) {
+        return Err(format!("{}s can't equip {}", class.name, item.name));
+    }
+    Ok(info)
+}
+
+impl Character {
+    // Puts the item on - returns whatever was in the slot before so it can go back in the bag
+    pub fn equip(&mut self, item: &ItemDef, class: &ClassDef) -> Result<Option<String>, String> {
+        let info = can_equip(item, class)?;
+        let old = self.equipment.slots.insert(info.slot, Equipped { item: item.id.clone(), mods: info.mods });
+        Ok(old.map(|e| e.item))
+    }
+
+    pub fn unequip(&mut self, slot: EquipSlot) -> Option<String> {
+        self.equipment.slots.remove(&slot).map(|e| e.item)
+    }
+
+    // What the character's numbers would look like with the item on instead
+    pub fn compare(&self, item: &ItemDef, class: &ClassDef) -> Result<EquipCompare, String> {
+        let info = can_equip(item, class)?;
+        let mut after = self.clone();
+        after.equipment.slots.insert(info.slot, Equipped { item: item.id.clone(), mods: info.mods });
+        let change = |f: fn(&Character) -> i32| StatChange { before: f(self), after: f(&after) };
+        Ok(EquipCompare {
+            slot: info.slot,
+            current: self.equipment.get(info.slot).map(|e| e.item.clone()),
+            attack: change(Character::attack),
+            defense: change(Character::defense),
+            speed: change(Character::speed),
+            magic: change(Character::magic),
+            healing: change(Character::healing),
+        })
+    }
+}
+
+// Menu-side helpers - gear moves between the party's bag and the character
+pub fn equip_from_inventory(
+    inventory: &mut Inventory,
+    character: &mut Character,
+    item: &ItemDef,
+    class: &ClassDef,
+) -> Result<(), String> {
+    if !inventory.has(&item.id) {
+        return Err(format!("No {} in the bag", item.name));
+    }
+    can_equip(item, class)?;
+    inventory.remove(&item.id, 1)?;
+    if let Some(old) = character.equip(item, class)? {
+        inventory.return_item(&old);
+    }
+    Ok(())
+}
+
+pub fn unequip_to_inventory(
+    inventory: &mut Inventory,
+    character: &mut Character,
+    slot: EquipSlot,
+) -> Result<(), String> {
+    let Some(equipped) = character.equipment.get(slot) else {
+        return Err(format!("Nothing equipped as {:?}", slot));
+    };
+    // Needs a free slot unless it stacks onto one already in the bag
+    if inventory.items.len() >= MAX_INVENTORY_SLOTS && !inventory.has(&equipped.item) {
+        return Err("The bag is full".to_string());
+    }
+    let Some(old) = character.unequip(slot) else { return Ok(()) };
+    inventory.return_item(&old);
+    Ok(())
+}
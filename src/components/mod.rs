@@ -1,20 +1,73 @@
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
 
 // Exposes most generic components that might be shared among multiple modules
+pub mod character;
+pub mod equipment;
 pub mod party;
+pub mod status;
 use party::*;
 
 // Most elements will typically have Z of 0, but sometimes something may be hidden in a tile on a different Z axis (Underground, Above)
 // Consider burrowing enemies or avian enemies - they might not be visible until you actually engage with them
-#[derive(Component, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
     pub z: i32,
 }
 
+// Which way something on the grid is looking - North is +y, same as 'up' on the minimap
+#[derive(Component, Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Facing {
+    #[default]
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Facing {
+    pub fn turn_left(self) -> Facing {
+        match self {
+            Facing::North => Facing::West,
+            Facing::West => Facing::South,
+            Facing::South => Facing::East,
+            Facing::East => Facing::North,
+        }
+    }
+
+    pub fn turn_right(self) -> Facing {
+        self.turn_left().reverse()
+    }
+
+    pub fn reverse(self) -> Facing {
+        self.turn_left().turn_left()
+    }
+
+    // One grid step in this direction
+    pub fn delta(self) -> (i32, i32) {
+        match self {
+            Facing::North => (0, 1),
+            Facing::East => (1, 0),
+            Facing::South => (0, -1),
+            Facing::West => (-1, 0),
+        }
+    }
+
+    // Direction code MapGrid::validate_move expects (Numpad layout - 8 is up)
+    pub fn numpad(self) -> i32 {
+        match self {
+            Facing::North => 8,
+            Facing::East => 6,
+            Facing::South => 2,
+            Facing::West => 4,
+        }
+    }
+}
+
 // This just serves as a method to 'hide' certain things, or tweak how it's displayed - can use a glyph from RLTK as placeholder until later when graphics are implemented
 #[derive(Component, Clone, Serialize, Deserialize)]
 pub struct Renderable {
@@ -33,9 +86,141 @@ pub struct InCombat;
 
 // Component for attaching a Health parameter to anything -
 // Centralized here since some non-living things may need health 
-#[derive(Component, Clone, Serialize, Deserialize)]
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
 pub struct Health{
     hp: i32,    // Could set it to unsigned int if we need more HP later
+    max_hp: i32,
+}
+
+impl Health {
+    // Starts at full HP
+    pub fn new(max_hp: i32) -> Health {
+        let max_hp = max_hp.max(1);
+        Health{ hp: max_hp, max_hp }
+    }
+
+    pub fn with_hp(hp: i32, max_hp: i32) -> Health {
+        let max_hp = max_hp.max(1);
+        Health{ hp: hp.clamp(0, max_hp), max_hp }
+    }
+
+    pub fn hp(&self) -> i32 {
+        self.hp
+    }
+
+    pub fn max_hp(&self) -> i32 {
+        self.max_hp
+    }
+
+    // Applies damage, never dropping below 0 - returns how much was actually taken
+    pub fn damage(&mut self, amount: i32) -> i32 {
+        let taken = amount.max(0).min(self.hp);
+        self.hp -= taken;
+        taken
+    }
+
+    // Heals up to max HP - returns how much was actually restored
+    // Doesn't bring back the dead, that's what revive is for
+    pub fn heal(&mut self, amount: i32) -> i32 {
+        if self.is_dead() {
+            return 0;
+        }
+        let healed = amount.max(0).min(self.max_hp - self.hp);
+        self.hp += healed;
+        healed
+    }
+
+    pub fn revive(&mut self, hp: i32) {
+        if self.is_dead() {
+            self.hp = hp.clamp(1, self.max_hp);
+        }
+    }
+
+    pub fn restore(&mut self) {
+        self.hp = self.max_hp;
+    }
+
+    // Current HP is clamped if the new max is lower
+    pub fn set_max_hp(&mut self, max_hp: i32) {
+        self.max_hp = max_hp.max(1);
+        self.hp = self.hp.min(self.max_hp);
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.hp <= 0
+    }
+}
+
+// Health events - `member` is the party slot when the HP belongs to a party member instead of the entity itself
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Damaged {
+    pub entity: Entity,
+    pub member: Option<usize>,
+    pub amount: i32,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Healed {
+    pub entity: Entity,
+    pub member: Option<usize>,
+    pub amount: i32,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct Died {
+    pub entity: Entity,
+    pub member: Option<usize>,
+}
+
+// Bundles the event writers so systems can change HP and report it in one call
+#[derive(SystemParam)]
+pub struct HealthEvents<'w> {
+    damaged: EventWriter<'w, Damaged>,
+    healed: EventWriter<'w, Healed>,
+    died: EventWriter<'w, Died>,
+}
+
+impl<'w> HealthEvents<'w> {
+    pub fn damage(&mut self, health: &mut Health, amount: i32, entity: Entity, member: Option<usize>) -> i32 {
+        let was_alive = !health.is_dead();
+        let taken = health.damage(amount);
+        if taken > 0 {
+            self.damaged.send(Damaged { entity, member, amount: taken });
+        }
+        if was_alive && health.is_dead() {
+            self.died.send(Died { entity, member });
+        }
+        taken
+    }
+
+    pub fn heal(&mut self, health: &mut Health, amount: i32, entity: Entity, member: Option<usize>) -> i32 {
+        let healed = health.heal(amount);
+        if healed > 0 {
+            self.healed.send(Healed { entity, member, amount: healed });
+        }
+        healed
+    }
+
+    // Moves HP straight to a value, reporting whichever direction it went
+    pub fn set_hp(&mut self, health: &mut Health, hp: i32, entity: Entity, member: Option<usize>) {
+        let diff = hp - health.hp();
+        if diff < 0 {
+            self.damage(health, -diff, entity, member);
+        } else if diff > 0 {
+            self.heal(health, diff, entity, member);
+        }
+    }
+}
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<Damaged>()
+            .add_event::<Healed>()
+            .add_event::<Died>();
+    }
 }
 
 
@@ -48,4 +233,5 @@ pub enum MenuButtonActions {
     Load,
     Undo,
     Redo,
+    MapInfo,
 }
\ No newline at end of file
@@ -2,88 +2,317 @@
 // I'm just using this as the 'object' to move around on the minimap
 
 use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
 use serde::{Deserialize, Serialize};
 use crate::components::*;
+use crate::components::character::*;
 use crate::minimap::*;
+use crate::resources::*;
+use crate::items::{starting_inventory, ItemLibrary};
+use crate::skills::SkillLibrary;
 
-#[derive(Component, Serialize, Deserialize, Clone)]
+// Marks the party the player controls - enemy parties share the Party component, so queries for 'our' party need this
+#[derive(Component, Clone, Copy, Default)]
+pub struct PlayerParty;
+
+pub const MAX_PARTY_SIZE: usize = 5;
+pub const MAX_ROW_SIZE: usize = 3;  // Front/back rows each hold at most this many
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Row {
+    #[default]
+    Front,
+    Back,
+}
+
+impl Row {
+    pub fn other(&self) -> Row {
+        match self {
+            Row::Front => Row::Back,
+            Row::Back => Row::Front,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PartyMember {
+    pub character: Character,
+    #[serde(default)]
+    pub row: Row,
+}
+
+impl PartyMember {
+    pub fn new(character: Character, row: Row) -> PartyMember {
+        PartyMember{ character, row }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.character.name
+    }
+}
+
+// Members are kept in marching order - index 0 is the first slot shown in menus
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
 pub struct Party {
     pub name: String,
-
+    #[serde(default)]
+    pub members: Vec<PartyMember>,
+    #[serde(default)]
+    leader: usize,
+    #[serde(default = "default_max_size")]
+    pub max_size: usize,
 }
 
+fn default_max_size() -> usize { MAX_PARTY_SIZE }
+
 // Couple of functions common to all parties (Player/Enemy)
 impl Party {
     pub fn new(name: &str) -> Party {
-        Party{name: name.to_string()}
+        Party{ name: name.to_string(), members: Vec::new(), leader: 0, max_size: MAX_PARTY_SIZE }
+    }
+
+    pub fn with_max_size(mut self, max_size: usize) -> Party {
+        self.max_size = max_size;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.members.len() >= self.max_size
+    }
+
+    pub fn row_count(&self, row: Row) -> usize {
+        self.members.iter().filter(|m| m.row == row).count()
+    }
+
+    pub fn row(&self, row: Row) -> impl Iterator<Item = &PartyMember> {
+        self.members.iter().filter(move |m| m.row == row)
+    }
+
+    // Adds to the end of the party - drops into the other row if the asked-for one is already full
+    // Returns the new member's index
+    pub fn add_member(&mut self, mut member: PartyMember) -> Result<usize, String> {
+        if self.is_full() {
+            return Err(format!("{} is full ({} members)", self.name, self.max_size));
+        }
+        if self.row_count(member.row) >= MAX_ROW_SIZE {
+            member.row = member.row.other();
+        }
+        self.members.push(member);
+        Ok(self.members.len() - 1)
+    }
+
+    // Leader passes to the next in line if they're the one leaving
+    pub fn remove_member(&mut self, index: usize) -> Result<PartyMember, String> {
+        if index >= self.members.len() {
+            return Err(format!("No member at slot {}", index));
+        }
+        let member = self.members.remove(index);
+        if self.leader > index || self.leader >= self.members.len() {
+            self.leader = self.leader.saturating_sub(1);
+        }
+        Ok(member)
+    }
+
+    // Moves a member to a new slot, shifting everyone in between - the leader stays the same person
+    pub fn move_member(&mut self, from: usize, to: usize) -> Result<(), String> {
+        if from >= self.members.len() || to >= self.members.len() {
+            return Err(format!("Can't move slot {} to {}", from, to));
+        }
+        let member = self.members.remove(from);
+        self.members.insert(to, member);
+        if self.leader == from {
+            self.leader = to;
+        } else if from < self.leader && self.leader <= to {
+            self.leader -= 1;
+        } else if to <= self.leader && self.leader < from {
+            self.leader += 1;
+        }
+        Ok(())
+    }
+
+    pub fn swap_members(&mut self, a: usize, b: usize) -> Result<(), String> {
+        if a >= self.members.len() || b >= self.members.len() {
+            return Err(format!("Can't swap slots {} and {}", a, b));
+        }
+        self.members.swap(a, b);
+        if self.leader == a {
+            self.leader = b;
+        } else if self.leader == b {
+            self.leader = a;
+        }
+        Ok(())
+    }
+
+    pub fn set_row(&mut self, index: usize, row: Row) -> Result<(), String> {
+        let Some(member) = self.members.get(index) else {
+            return Err(format!("No member at slot {}", index));
+        };
+        if member.row != row && self.row_count(row) >= MAX_ROW_SIZE {
+            return Err(format!("The {:?} row is full", row));
+        }
+        self.members[index].row = row;
+        Ok(())
+    }
+
+    pub fn leader_index(&self) -> Option<usize> {
+        if self.members.is_empty() { None } else { Some(self.leader) }
+    }
+
+    pub fn leader(&self) -> Option<&PartyMember> {
+        self.members.get(self.leader)
+    }
+
+    pub fn living(&self) -> impl Iterator<Item = &PartyMember> {
+        self.members.iter().filter(|m| m.character.is_alive())
     }
 
-    
-    // pub fn add_member();
-    // pub fn remove_member(index: usize);
+    pub fn is_wiped(&self) -> bool {
+        self.living().next().is_none()
+    }
 
+    pub fn set_leader(&mut self, index: usize) -> Result<(), String> {
+        if index >= self.members.len() {
+            return Err(format!("No member at slot {}", index));
+        }
+        self.leader = index;
+        Ok(())
+    }
+}
+
+// TODO - placeholder roster until there's a guild/party creation screen
+fn demo_party(classes: &ClassLibrary, skills: &SkillLibrary) -> Party {
+    let mut party = Party::new("Demo");
+    let roster = [
+        ("Aria", "fighter", Row::Front),
+        ("Bram", "guardian", Row::Front),
+        ("Cole", "ranger", Row::Back),
+        ("Dana", "medic", Row::Back),
+    ];
+    for (name, class, row) in roster {
+        let Some(class) = classes.get(class) else {
+            println!("No class named {} - leaving {} out of the party", class, name);
+            continue;
+        };
+        let mut character = Character::new(name, class);
+        // Everyone starts out knowing the first skill in their tree
+        if let Some(skill) = class.skills.first().and_then(|id| skills.get(id)) {
+            if let Err(e) = character.learn_skill(skill, class) {
+                println!("{}", e);
+            }
+        }
+        let _ = party.add_member(PartyMember::new(character, row));
+    }
+    party
 }
 
 // Creates a party Entity for us to use, along with a placeholder sprite
-pub fn party_setup(mut commands: Commands) {
+pub fn party_setup(
+    mut commands: Commands,
+    mg: Res<MapGrid>,
+    classes: Res<ClassLibrary>,
+    skills: Res<SkillLibrary>,
+    items: Res<ItemLibrary>,
+) {
+    let start = Position{ x: 0, y: 0, z: 0 };
+    let world = grid_to_world(&mg, &start);
     commands.spawn((
         SpriteBundle{
             sprite: Sprite { color: Color::BLACK, custom_size: (Some(Vec2::new(1.0,1.0))), ..Default::default() },
                 visibility: Visibility::Visible,
                 transform: Transform {
-                    translation: Vec2::new(0., 0.).extend(10.0),
-                    scale: Vec3::new(7., 7., 0.),
+                    translation: world.extend(10.0),
+                    scale: Vec3::new(7., 7., 1.),
                     ..default()
                 },
                 ..Default::default()
         },
-        Party{
-            name: "Demo".to_string(),
-        },
-        Position{
-            x: 0, y: 0, z: 0
-        }
-    ));
+        demo_party(&classes, &skills),
+        starting_inventory(&items),
+        PlayerParty,
+        start,
+        Facing::default(),
+        RenderLayers::layer(2),
+    )).with_children(|parent| {
+        // Facing marker - sits on the front edge, so it turns with the party
+        parent.spawn((
+            SpriteBundle{
+                sprite: Sprite { color: Color::ORANGE_RED, custom_size: (Some(Vec2::new(1.0,1.0))), ..Default::default() },
+                transform: Transform {
+                    translation: Vec3::new(0., 0.5, 0.1),
+                    scale: Vec3::new(0.5, 0.35, 1.),
+                    ..default()
+                },
+                ..Default::default()
+            },
+            RenderLayers::layer(2),
+        ));
+    });
 }
 
 
 
+// Runs in TurnPhase::Input - movement is relative to the way the party faces
+// W/S (Up/Down) step forward/back, A/D (Left/Right) turn on the spot, Z/C strafe left/right
+// Turning is free, a successful step consumes the party's turn
 // TODO - move this into a plugin to bundle it up neatly
 pub fn party_movement_minimap(
-    // TODO - modify query with component that denotes our party specifically.
-    mut party: Query<(&Party, &mut Position, &mut Transform)>,
+    mut party: Query<(&mut Position, &mut Facing, &mut Transform), With<PlayerParty>>,
     input: Res<Input<KeyCode>>,
     mg: Res<MapGrid>,
+    mut next_turn: ResMut<NextState<TurnState>>,
 ){
-    let (party, mut pos, mut transform) = party.get_single_mut().expect("More than 1 party matched");
-    
-    // TODO - rewrite so we use a nice match statement?
-    if input.any_pressed([KeyCode::W, KeyCode::Up]){
-        // Up direction was pressed - validate and move if passed
-        if mg.validate_move(&pos, 8).unwrap() {
-            pos.y = pos.y + 1;
-            transform.translation.y += mg.zoom;
-        }
+    let (mut pos, mut facing, mut transform) = party.get_single_mut().expect("More than 1 party matched");
+
+    if input.any_just_pressed([KeyCode::A, KeyCode::Left]) {
+        *facing = facing.turn_left();
+        return;
     }
-    if input.any_pressed([KeyCode::A, KeyCode::Left]){
-        // Left direction was pressed
-        if mg.validate_move(&pos, 4).unwrap() {
-            pos.x = pos.x - 1;
-            transform.translation.x -= mg.zoom;
-        }
+    if input.any_just_pressed([KeyCode::D, KeyCode::Right]) {
+        *facing = facing.turn_right();
+        return;
     }
-    if input.any_pressed([KeyCode::S, KeyCode::Down]){
-        // Down direction was pressed
-        if mg.validate_move(&pos, 2).unwrap() {
-            pos.y = pos.y - 1;
-            transform.translation.y -= mg.zoom;
-        }
+
+    // Only one step per turn - first direction pressed wins
+    let dir = if input.any_just_pressed([KeyCode::W, KeyCode::Up]) {
+        *facing
+    } else if input.any_just_pressed([KeyCode::S, KeyCode::Down]) {
+        facing.reverse()
+    } else if input.just_pressed(KeyCode::Z) {
+        facing.turn_left()
+    } else if input.just_pressed(KeyCode::C) {
+        facing.turn_right()
+    } else {
+        return;
+    };
+    let (dx, dy) = dir.delta();
+
+    // Validate and move if passed - bumping into a wall doesn't use up the turn
+    if mg.validate_move(&pos, dir.numpad()).unwrap() {
+        pos.x = pos.x + dx;
+        pos.y = pos.y + dy;
+        transform.translation.x += dx as f32 * mg.zoom;
+        transform.translation.y += dy as f32 * mg.zoom;
+        next_turn.set(TurnState::PlayerTurn);
     }
-    if input.any_pressed([KeyCode::D, KeyCode::Right]){
-        // Right direction was pressed
-        if mg.validate_move(&pos, 6).unwrap() {
-            pos.x = pos.x + 1;
-            transform.translation.x += mg.zoom;
-        }
+}
+
+// Points the minimap marker the way the party faces - the nub on top of the sprite is the front
+pub fn update_facing_marker(mut party: Query<(&Facing, &mut Transform), (With<PlayerParty>, Changed<Facing>)>) {
+    for (facing, mut transform) in party.iter_mut() {
+        let turns = match facing {
+            Facing::North => 0.,
+            Facing::East => -1.,
+            Facing::South => 2.,
+            Facing::West => 1.,
+        };
+        transform.rotation = Quat::from_rotation_z(turns * std::f32::consts::FRAC_PI_2);
     }
-}
\ No newline at end of file
+}
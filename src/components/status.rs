@@ -0,0 +1,240 @@
+// Status effects - ailments (Poison, sleep, ...) and stat buffs/debuffs, each with a duration in turns
+// StatusEffects is a component for anything on the map, and is also carried by Characters and Combatants
+// Durations tick on TurnPhase::Other while exploring, and at the end of each round in combat
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{party::{Party, PlayerParty}, Health, HealthEvents};
+use crate::resources::GameRng;
+use crate::turn::TurnPhase;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BuffStat {
+    Attack,
+    Defense,
+    Speed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StatusKind {
+    Poison,     // Potency damage every turn (Every step while exploring)
+    Paralysis,  // Potency% chance to lose the turn
+    Sleep,      // Loses every turn, wakes up when hit
+    Blind,      // Potency% chance for attacks to miss
+    Buff(BuffStat),    // +Potency% to the stat
+    Debuff(BuffStat),  // -Potency% to the stat
+}
+
+impl StatusKind {
+    // Poison is the only thing that follows the party out of a fight
+    pub fn persists_outside_combat(&self) -> bool {
+        matches!(self, StatusKind::Poison)
+    }
+
+    pub fn is_ailment(&self) -> bool {
+        matches!(self, StatusKind::Poison | StatusKind::Paralysis | StatusKind::Sleep | StatusKind::Blind)
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            StatusKind::Buff(stat) => format!("{:?} Up", stat),
+            StatusKind::Debuff(stat) => format!("{:?} Down", stat),
+            other => format!("{:?}", other),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub turns: u32,
+    pub potency: i32,
+}
+
+impl StatusEffect {
+    pub fn new(kind: StatusKind, turns: u32, potency: i32) -> Self {
+        StatusEffect { kind, turns, potency }
+    }
+}
+
+// Something that happened when the effects ticked over
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusTick {
+    Damage { kind: StatusKind, amount: i32 },
+    Expired(StatusKind),
+}
+
+// Why a combatant lost their turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Incapacitated {
+    Asleep,
+    Paralyzed,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.effects.iter().any(|e| e.kind == kind)
+    }
+
+    pub fn get(&self, kind: StatusKind) -> Option<&StatusEffect> {
+        self.effects.iter().find(|e| e.kind == kind)
+    }
+
+    // Stacking rules:
+    //  - The same effect doesn't stack, it refreshes to whichever duration/potency is higher
+    //  - A buff and a debuff on the same stat cancel each other out instead of both applying
+    // Returns false if the effect had nothing to do
+    pub fn apply(&mut self, effect: StatusEffect) -> bool {
+        if effect.turns == 0 {
+            return false;
+        }
+        let opposite = match effect.kind {
+            StatusKind::Buff(stat) => Some(StatusKind::Debuff(stat)),
+            StatusKind::Debuff(stat) => Some(StatusKind::Buff(stat)),
+            _ => None,
+        };
+        if let Some(opposite) = opposite {
+            if self.has(opposite) {
+                self.remove(opposite);
+                return true;
+            }
+        }
+        if let Some(existing) = self.effects.iter_mut().find(|e| e.kind == effect.kind) {
+            existing.turns = existing.turns.max(effect.turns);
+            existing.potency = existing.potency.max(effect.potency);
+            return true;
+        }
+        self.effects.push(effect);
+        true
+    }
+
+    pub fn remove(&mut self, kind: StatusKind) -> bool {
+        let before = self.effects.len();
+        self.effects.retain(|e| e.kind != kind);
+        self.effects.len() != before
+    }
+
+    pub fn clear_ailments(&mut self) {
+        self.effects.retain(|e| !e.kind.is_ailment());
+    }
+
+    // Drops anything that shouldn't carry over once a battle ends
+    pub fn end_combat(&mut self) {
+        self.effects.retain(|e| e.kind.persists_outside_combat());
+    }
+
+    // One turn passes - returns the poison damage to apply and whatever wore off
+    pub fn tick(&mut self) -> Vec<StatusTick> {
+        let mut ticks = Vec::new();
+        for effect in self.effects.iter_mut() {
+            if effect.kind == StatusKind::Poison {
+                ticks.push(StatusTick::Damage { kind: effect.kind, amount: effect.potency.max(1) });
+            }
+            effect.turns = effect.turns.saturating_sub(1);
+            if effect.turns == 0 {
+                ticks.push(StatusTick::Expired(effect.kind));
+            }
+        }
+        self.effects.retain(|e| e.turns > 0);
+        ticks
+    }
+
+    // Sleep always stops the turn, paralysis only some of the time
+    pub fn incapacitated(&self, rng: &mut GameRng) -> Option<Incapacitated> {
+        if self.has(StatusKind::Sleep) {
+            return Some(Incapacitated::Asleep);
+        }
+        if let Some(paralysis) = self.get(StatusKind::Paralysis) {
+            if rng.roll(paralysis.potency) {
+                return Some(Incapacitated::Paralyzed);
+            }
+        }
+        None
+    }
+
+    pub fn misses(&self, rng: &mut GameRng) -> bool {
+        self.get(StatusKind::Blind).map(|b| rng.roll(b.potency)).unwrap_or(false)
+    }
+
+    // Applies buffs/debuffs to a stat value - never drops it below 1
+    pub fn modify(&self, stat: BuffStat, value: i32) -> i32 {
+        let percent: i32 = self.effects.iter()
+            .map(|e| match e.kind {
+                StatusKind::Buff(s) if s == stat => e.potency,
+                StatusKind::Debuff(s) if s == stat => -e.potency,
+                _ => 0,
+            })
+            .sum();
+        (value * (100 + percent) / 100).max(1)
+    }
+}
+
+// Chance to pass an effect on - used by enemy attacks (And skills later on)
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StatusInflict {
+    #[serde(flatten)]
+    pub effect: StatusEffect,
+    pub chance: i32,
+}
+
+// Field poison stops at 1 HP - walking around shouldn't be able to wipe the party
+const FIELD_POISON_FLOOR: i32 = 1;
+
+// TurnPhase::Other - party members' effects tick once per step while exploring
+pub fn tick_party_status(
+    mut party: Query<(Entity, &mut Party), With<PlayerParty>>,
+    mut health_events: HealthEvents,
+) {
+    let Ok((entity, mut party)) = party.get_single_mut() else { return };
+    for (slot, member) in party.members.iter_mut().enumerate() {
+        let character = &mut member.character;
+        if !character.is_alive() {
+            continue;
+        }
+        for tick in character.status.tick() {
+            match tick {
+                StatusTick::Damage { kind, amount } => {
+                    let amount = amount.min(character.health.hp() - FIELD_POISON_FLOOR);
+                    let taken = health_events.damage(&mut character.health, amount, entity, Some(slot));
+                    if taken > 0 {
+                        println!("{} takes {} damage from {}", character.name, taken, kind.label());
+                    }
+                }
+                StatusTick::Expired(kind) => println!("{}'s {} wore off", character.name, kind.label()),
+            }
+        }
+    }
+}
+
+// TurnPhase::Other - same thing for anything on the map carrying its own StatusEffects
+pub fn tick_status_effects(
+    mut query: Query<(Entity, &mut StatusEffects, Option<&mut Health>)>,
+    mut health_events: HealthEvents,
+) {
+    for (entity, mut status, health) in query.iter_mut() {
+        if status.effects.is_empty() {
+            continue;
+        }
+        let ticks = status.tick();
+        let Some(mut health) = health else { continue };
+        for tick in ticks {
+            if let StatusTick::Damage { amount, .. } = tick {
+                health_events.damage(&mut health, amount, entity, None);
+            }
+        }
+    }
+}
+
+pub struct StatusPlugin;
+
+impl Plugin for StatusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (tick_party_status, tick_status_effects).in_set(TurnPhase::Other));
+    }
+}
@@ -0,0 +1,318 @@
+// Branching dialogue - conversation trees loaded from assets/data/dialogue/*.json
+// An Interactable NPC next to the party starts its conversation on the interact key
+// Shares the text box and GameplayState::Dialogue with scripted events, so exploration input is paused the same way
+
+use std::collections::HashMap;
+use std::fs::{read_dir, File};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{party::PlayerParty, Interactable, Position};
+use crate::minimap::*;
+use crate::resources::*;
+use crate::scripting::*;
+use crate::textbox::*;
+use crate::turn::TurnPhase;
+
+pub const DIALOGUE_DIR: &str = "assets/data/dialogue";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DialogueChoice {
+    pub text: String,
+    #[serde(default)]
+    pub next: Option<String>,       // None ends the conversation
+    #[serde(default)]
+    pub requires: Vec<String>,      // Flags that must be set for this choice to show up
+    #[serde(default)]
+    pub requires_not: Vec<String>,  // Flags that must NOT be set
+}
+
+impl DialogueChoice {
+    pub fn available(&self, flags: &StoryFlags) -> bool {
+        self.requires.iter().all(|f| flags.get(f)) && !self.requires_not.iter().any(|f| flags.get(f))
+    }
+}
+
+// Node hooks are plain scripting steps - only the ones that finish straight away make sense on entering a node
+fn is_hook(step: &EventStep) -> bool {
+    matches!(step, EventStep::SetFlag { .. } | EventStep::GiveItem { .. } | EventStep::StartBattle { .. })
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DialogueNode {
+    #[serde(default)]
+    pub speaker: Option<String>,
+    pub text: String,
+    #[serde(default)]
+    pub choices: Vec<DialogueChoice>,
+    #[serde(default)]
+    pub next: Option<String>,   // Used when there are no choices - None ends the conversation
+    #[serde(default)]
+    pub on_enter: Vec<EventStep>,   // Run when the node is entered - see is_hook for what's allowed
+    #[serde(default)]
+    pub signals: Vec<String>,       // Free-form hooks for systems listening to DialogueNodeEntered
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DialogueTree {
+    pub id: String,
+    pub start: String,
+    pub nodes: HashMap<String, DialogueNode>,
+}
+
+#[derive(Resource, Default)]
+pub struct DialogueLibrary {
+    pub trees: HashMap<String, DialogueTree>,
+}
+
+impl DialogueLibrary {
+    // Every .json file in the directory is one conversation tree
+    pub fn load_from_dir(path: &str) -> Result<Self, String> {
+        let mut lib = DialogueLibrary::default();
+        let entries = read_dir(path).map_err(|e| format!("Unable to read dialogue dir {}: {}", path, e))?;
+        for entry in entries.flatten() {
+            let file_path = entry.path();
+            if file_path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let file = File::open(&file_path).map_err(|e| format!("Unable to open {:?}: {}", file_path, e))?;
+            let tree: DialogueTree = serde_json::from_reader(file)
+                .map_err(|e| format!("Unable to parse {:?}: {}", file_path, e))?;
+            for (node_id, node) in &tree.nodes {
+                if let Some(step) = node.on_enter.iter().find(|s| !is_hook(s)) {
+                    return Err(format!("Dialogue {} node {} can't run {:?} on enter", tree.id, node_id, step));
+                }
+            }
+            lib.trees.insert(tree.id.clone(), tree);
+        }
+        Ok(lib)
+    }
+
+    pub fn get(&self, id: &str) -> Option<&DialogueTree> {
+        self.trees.get(id)
+    }
+}
+
+// NPC that can be talked to - needs Interactable and a Position to be found by the party
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct Npc {
+    pub name: String,
+    pub dialogue: String,  // Id of the DialogueTree
+}
+
+// Sent every time a node is entered, after its built-in hooks have run
+#[derive(Event, Clone, Debug)]
+pub struct DialogueNodeEntered {
+    pub tree: String,
+    pub node: String,
+    pub signals: Vec<String>,
+}
+
+// Conversation in progress
+#[derive(Resource, Clone, Debug)]
+pub struct ActiveDialogue {
+    pub tree: String,
+    pub node: String,
+    pub speaker: Option<String>,  // Falls back to the NPC's name when a node has no speaker
+    visible_choices: Vec<usize>,  // Indices into the node's choices that passed their flag checks
+    pub exit_to: TurnState,
+}
+
+pub fn load_dialogue_library(mut commands: Commands) {
+    match DialogueLibrary::load_from_dir(DIALOGUE_DIR) {
+        Ok(lib) => commands.insert_resource(lib),
+        Err(e) => {
+            println!("{} - continuing without dialogue", e);
+            commands.insert_resource(DialogueLibrary::default());
+        }
+    }
+}
+
+pub fn spawn_npc(commands: &mut Commands, mg: &MapGrid, name: &str, dialogue: &str, pos: Position) {
+    let world = grid_to_world(mg, &pos);
+    commands.spawn((
+        SpriteBundle{
+            sprite: Sprite { color: Color::GOLD, custom_size: (Some(Vec2::new(1.0,1.0))), ..Default::default() },
+            visibility: Visibility::Visible,
+            transform: Transform {
+                translation: world.extend(9.0),
+                scale: Vec3::new(6., 6., 0.),
+                ..default()
+            },
+            ..Default::default()
+        },
+        Npc { name: name.to_string(), dialogue: dialogue.to_string() },
+        Interactable,
+        pos,
+    ));
+}
+
+// Can the party reach the NPC to talk - same tile, or next door with no wall in the way
+fn within_reach(mg: &MapGrid, party: &Position, other: &Position) -> bool {
+    let dir = match (other.x - party.x, other.y - party.y) {
+        (0, 0) => return true,
+        (0, -1) => 2,
+        (-1, 0) => 4,
+        (1, 0) => 6,
+        (0, 1) => 8,
+        _ => return false,
+    };
+    mg.validate_move(party, dir).unwrap_or(false)
+}
+
+// Interact key (E) starts a conversation with the first NPC in reach
+pub fn start_dialogue_on_interact(
+    mut commands: Commands,
+    input: Res<Input<KeyCode>>,
+    party: Query<&Position, With<PlayerParty>>,
+    npcs: Query<(&Position, &Npc), With<Interactable>>,
+    mg: Res<MapGrid>,
+    lib: Res<DialogueLibrary>,
+    mut flags: ResMut<StoryFlags>,
+    mut entered: EventWriter<DialogueNodeEntered>,
+    mut actions: EventWriter<ScriptActionEvent>,
+    mut next_turn: ResMut<NextState<TurnState>>,
+    mut next_gameplay: ResMut<NextState<GameplayState>>,
+) {
+    if !input.just_pressed(KeyCode::E) {
+        return;
+    }
+    let Ok(party_pos) = party.get_single() else { return };
+    let Some((_, npc)) = npcs.iter().find(|(pos, _)| within_reach(&mg, party_pos, pos)) else { return };
+    let Some(tree) = lib.get(&npc.dialogue) else {
+        println!("{} has no dialogue named {}", npc.name, npc.dialogue);
+        return;
+    };
+
+    let mut dialogue = ActiveDialogue {
+        tree: tree.id.clone(),
+        node: tree.start.clone(),
+        speaker: Some(npc.name.clone()),
+        visible_choices: Vec::new(),
+        exit_to: TurnState::AwaitingInput,
+    };
+    if enter_node(&mut commands, &mut dialogue, tree, &tree.start, &mut flags, &mut entered, &mut actions) {
+        commands.insert_resource(dialogue);
+        next_turn.set(TurnState::EnterDialogue);
+        next_gameplay.set(GameplayState::Dialogue);
+    }
+}
+
+// Moves the conversation to a node - runs its hooks and puts up the text box
+// Returns false if the node doesn't exist (The conversation should end)
+fn enter_node(
+    commands: &mut Commands,
+    dialogue: &mut ActiveDialogue,
+    tree: &DialogueTree,
+    node_id: &str,
+    flags: &mut StoryFlags,
+    entered: &mut EventWriter<DialogueNodeEntered>,
+    actions: &mut EventWriter<ScriptActionEvent>,
+) -> bool {
+    let Some(node) = tree.nodes.get(node_id) else {
+        println!("Dialogue {} has no node {}", tree.id, node_id);
+        return false;
+    };
+
+    for step in &node.on_enter {
+        match step {
+            EventStep::SetFlag { flag, value } => flags.set(flag, *value),
+            EventStep::GiveItem { item, qty } => {
+                actions.send(ScriptActionEvent(ScriptAction::GiveItem { item: item.clone(), qty: *qty }));
+            }
+            EventStep::StartBattle { encounter } => {
+                actions.send(ScriptActionEvent(ScriptAction::StartBattle { encounter: encounter.clone() }));
+                dialogue.exit_to = TurnState::EnterCombat;
+            }
+            _ => {}  // Anything else is turned away when the library loads
+        }
+    }
+    entered.send(DialogueNodeEntered { tree: tree.id.clone(), node: node_id.to_string(), signals: node.signals.clone() });
+
+    dialogue.node = node_id.to_string();
+    dialogue.visible_choices = node.choices.iter().enumerate()
+        .filter(|(_, c)| c.available(flags))
+        .map(|(i, _)| i)
+        .collect();
+    let choice_text: Vec<String> = dialogue.visible_choices.iter().map(|i| node.choices[*i].text.clone()).collect();
+    let speaker = node.speaker.clone().or_else(|| dialogue.speaker.clone());
+    spawn_textbox(commands, speaker.as_deref(), &node.text, &choice_text);
+    true
+}
+
+// Waits on the player's response and walks the tree
+pub fn run_dialogue(
+    mut commands: Commands,
+    mut dialogue: ResMut<ActiveDialogue>,
+    lib: Res<DialogueLibrary>,
+    mut flags: ResMut<StoryFlags>,
+    mut inputs: EventReader<TextBoxInput>,
+    boxes: Query<Entity, With<TextBox>>,
+    mut entered: EventWriter<DialogueNodeEntered>,
+    mut actions: EventWriter<ScriptActionEvent>,
+    mut next_turn: ResMut<NextState<TurnState>>,
+    mut next_gameplay: ResMut<NextState<GameplayState>>,
+) {
+    let current = lib.get(&dialogue.tree)
+        .and_then(|tree| tree.nodes.get(&dialogue.node).map(|node| (tree, node)));
+    let Some((tree, node)) = current else {
+        despawn_textbox(&mut commands, &boxes);
+        end_dialogue(&mut commands, &dialogue, &mut next_turn, &mut next_gameplay);
+        return;
+    };
+
+    // Work out where the player's response takes us - Some(None) means the conversation is over
+    let mut destination: Option<Option<String>> = None;
+    for input in inputs.read() {
+        match input {
+            TextBoxInput::Confirm if dialogue.visible_choices.is_empty() => {
+                destination = Some(node.next.clone());
+            }
+            TextBoxInput::Choose(i) => {
+                if let Some(choice_index) = dialogue.visible_choices.get(*i) {
+                    destination = Some(node.choices[*choice_index].next.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+    let Some(destination) = destination else { return };
+
+    despawn_textbox(&mut commands, &boxes);
+    let continued = match destination {
+        Some(next) => enter_node(&mut commands, &mut dialogue, tree, &next, &mut flags, &mut entered, &mut actions),
+        None => false,
+    };
+    if !continued {
+        end_dialogue(&mut commands, &dialogue, &mut next_turn, &mut next_gameplay);
+    }
+}
+
+// Text box should already be gone by the time this is called
+fn end_dialogue(
+    commands: &mut Commands,
+    dialogue: &ActiveDialogue,
+    next_turn: &mut NextState<TurnState>,
+    next_gameplay: &mut NextState<GameplayState>,
+) {
+    commands.remove_resource::<ActiveDialogue>();
+    next_gameplay.set(GameplayState::Exploration);
+    next_turn.set(dialogue.exit_to);
+}
+
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<StoryFlags>()
+            .add_event::<DialogueNodeEntered>()
+            .add_systems(Startup, load_dialogue_library)
+            .add_systems(Update, start_dialogue_on_interact.in_set(TurnPhase::Input))
+            .add_systems(Update, run_dialogue
+                .after(textbox_input)
+                .run_if(in_state(GameplayState::Dialogue))
+                .run_if(resource_exists::<ActiveDialogue>()));
+    }
+}
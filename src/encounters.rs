@@ -0,0 +1,282 @@
+// Random encounters - weighted enemy groups rolled as the party walks around
+// Each map points at a table through MapMetadata.encounter_table, and a tile can override it with the
+// "encounter_table" tile property (Set it to "none" to make a safe spot, like a camp or a shrine room)
+// Every step without a fight raises the odds of one, and the result is exposed through EncounterDanger for the HUD
+
+use std::collections::HashMap;
+use std::fs::File;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{party::PlayerParty, Position};
+use crate::minimap::*;
+use crate::resources::*;
+use crate::enemies::FoeContact;
+use crate::dialogue::run_dialogue;
+use crate::scripting::{check_event_tiles, run_event, ScriptAction, ScriptActionEvent};
+use crate::turn::TurnPhase;
+
+pub const ENCOUNTER_FILE: &str = "assets/data/encounters.json";
+pub const ENCOUNTER_PROP: &str = "encounter_table";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EncounterGroup {
+    pub encounter: String,  // Id of the enemy group handed to combat
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 { 1 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EncounterTable {
+    pub id: String,
+    #[serde(default)]
+    pub base_rate: i32,       // Percent chance on the first step after a fight
+    #[serde(default = "default_step_increase")]
+    pub step_increase: i32,   // Added to the chance for every step taken without one
+    #[serde(default = "default_max_rate")]
+    pub max_rate: i32,
+    #[serde(default)]
+    pub grace_steps: u32,     // Free steps right after a fight before anything can be rolled
+    pub groups: Vec<EncounterGroup>,
+}
+
+fn default_step_increase() -> i32 { 2 }
+fn default_max_rate() -> i32 { 50 }
+
+impl EncounterTable {
+    // Chance (Percent) of a fight on the given step since the last one
+    pub fn chance(&self, steps: u32) -> i32 {
+        if steps <= self.grace_steps {
+            return 0;
+        }
+        let walked = (steps - self.grace_steps) as i32;
+        (self.base_rate + self.step_increase * (walked - 1)).clamp(0, self.max_rate)
+    }
+
+    // Weighted pick of the enemy group to fight
+    pub fn pick_group(&self, rng: &mut GameRng) -> Option<String> {
+        let total: u32 = self.groups.iter().map(|g| g.weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = rng.range(0, total as i32) as u32;
+        for group in &self.groups {
+            if roll < group.weight {
+                return Some(group.encounter.clone());
+            }
+            roll -= group.weight;
+        }
+        None
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct EncounterTables {
+    pub tables: HashMap<String, EncounterTable>,
+}
+
+impl EncounterTables {
+    pub fn load_from_file(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Unable to open {}: {}", path, e))?;
+        let tables: Vec<EncounterTable> = serde_json::from_reader(file)
+            .map_err(|e| format!("Unable to parse {}: {}", path, e))?;
+        Ok(EncounterTables { tables: tables.into_iter().map(|t| (t.id.clone(), t)).collect() })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&EncounterTable> {
+        self.tables.get(id)
+    }
+
+    // Table in effect at a spot - the tile's property wins over the map's metadata
+    pub fn table_at(&self, mg: &MapGrid, meta: Option<&MapMetadata>, pos: &Position) -> Option<&EncounterTable> {
+        let id = match mg.prop_at(pos, ENCOUNTER_PROP).and_then(|p| p.as_str()) {
+            Some(id) => Some(id.to_string()),
+            None => meta.and_then(|m| m.encounter_table.clone()),
+        }?;
+        if id.is_empty() || id == "none" {
+            return None;
+        }
+        self.get(&id)
+    }
+}
+
+// Rough bands for the HUD's danger indicator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DangerLevel {
+    Safe,
+    Low,
+    Medium,
+    High,
+}
+
+impl DangerLevel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DangerLevel::Safe => "Safe",
+            DangerLevel::Low => "Low",
+            DangerLevel::Medium => "Medium",
+            DangerLevel::High => "High",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            DangerLevel::Safe => Color::CYAN,
+            DangerLevel::Low => Color::GREEN,
+            DangerLevel::Medium => Color::YELLOW,
+            DangerLevel::High => Color::RED,
+        }
+    }
+}
+
+// Encounter state for the current walk - steps since the last fight and the odds on the next step
+#[derive(Resource, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EncounterDanger {
+    pub steps: u32,
+    pub chance: i32,                  // Percent chance on the NEXT step
+    pub table: Option<String>,        // Table in effect where the party is standing
+}
+
+impl EncounterDanger {
+    pub fn level(&self) -> DangerLevel {
+        match self.chance {
+            c if self.table.is_none() || c <= 0 => DangerLevel::Safe,
+            c if c < 10 => DangerLevel::Low,
+            c if c < 25 => DangerLevel::Medium,
+            _ => DangerLevel::High,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.steps = 0;
+    }
+}
+
+pub fn load_encounter_tables(mut commands: Commands) {
+    match EncounterTables::load_from_file(ENCOUNTER_FILE) {
+        Ok(tables) => commands.insert_resource(tables),
+        Err(e) => {
+            println!("{} - continuing without random encounters", e);
+            commands.insert_resource(EncounterTables::default());
+        }
+    }
+}
+
+// Queues up a fight - shared by random encounters, alarms and scripted battles
+pub fn start_battle(
+    commands: &mut Commands,
+    encounter: Option<String>,
+    next_turn: &mut NextState<TurnState>,
+    next_combat: &mut NextState<CombatState>,
+) {
+    commands.insert_resource(PendingBattle { encounter, foe: None });
+    next_turn.set(TurnState::EnterCombat);
+    next_combat.set(CombatState::EnteredCombat);
+}
+
+// TurnPhase::Player - rolls for a fight after each step the party takes
+pub fn roll_random_encounter(
+    mut commands: Commands,
+    party: Query<&Position, (With<PlayerParty>, Changed<Position>)>,
+    tables: Res<EncounterTables>,
+    mg: Res<MapGrid>,
+    meta: Option<Res<MapMetadata>>,
+    mut danger: ResMut<EncounterDanger>,
+    mut rng: ResMut<GameRng>,
+    mut next_turn: ResMut<NextState<TurnState>>,
+    mut next_combat: ResMut<NextState<CombatState>>,
+) {
+    let Ok(pos) = party.get_single() else { return };
+    // Something else on this tile (A transition, trap, event or foe) already took over the turn
+    if next_turn.0.is_some() {
+        return;
+    }
+    let Some(table) = tables.table_at(&mg, meta.as_deref(), pos) else {
+        danger.table = None;
+        danger.chance = 0;
+        return;
+    };
+
+    danger.steps += 1;
+    danger.table = Some(table.id.clone());
+    if rng.roll(table.chance(danger.steps)) {
+        if let Some(group) = table.pick_group(&mut rng) {
+            println!("Encounter! ({})", group);
+            danger.reset();
+            start_battle(&mut commands, Some(group), &mut next_turn, &mut next_combat);
+        }
+    }
+    danger.chance = table.chance(danger.steps + 1);
+}
+
+// Battles asked for by scripts/dialogue - an empty encounter gets filled in from the local table
+pub fn start_scripted_battles(
+    mut commands: Commands,
+    mut actions: EventReader<ScriptActionEvent>,
+    party: Query<&Position, With<PlayerParty>>,
+    tables: Res<EncounterTables>,
+    mg: Res<MapGrid>,
+    meta: Option<Res<MapMetadata>>,
+    mut rng: ResMut<GameRng>,
+    mut next_combat: ResMut<NextState<CombatState>>,
+) {
+    for ScriptActionEvent(action) in actions.read() {
+        let ScriptAction::StartBattle { encounter } = action else { continue };
+        let encounter = encounter.clone().or_else(|| {
+            let pos = party.get_single().ok()?;
+            tables.table_at(&mg, meta.as_deref(), pos)?.pick_group(&mut rng)
+        });
+        // The script/dialogue moves the turn into EnterCombat itself once it wraps up
+        commands.insert_resource(PendingBattle { encounter, foe: None });
+        next_combat.set(CombatState::EnteredCombat);
+    }
+}
+
+// Small readout in the corner - just the danger band for now
+#[derive(Component)]
+pub struct DangerText;
+
+pub fn danger_hud_setup(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section("", TextStyle { font_size: 18.0, color: Color::WHITE, ..default() })
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.),
+                right: Val::Px(8.),
+                ..default()
+            }),
+        DangerText,
+    ));
+}
+
+pub fn danger_hud_update(danger: Res<EncounterDanger>, mut text: Query<&mut Text, With<DangerText>>) {
+    if !danger.is_changed() {
+        return;
+    }
+    let level = danger.level();
+    for mut text in text.iter_mut() {
+        text.sections[0].value = format!("Danger: {}", level.label());
+        text.sections[0].style.color = level.color();
+    }
+}
+
+pub struct EncounterPlugin;
+
+impl Plugin for EncounterPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<EncounterDanger>()
+            .add_systems(Startup, (load_encounter_tables, danger_hud_setup))
+            .add_systems(Update, roll_random_encounter
+                .in_set(TurnPhase::Player)
+                .after(check_transition_tiles)
+                .after(check_trap_tiles)
+                .after(check_event_tiles)
+                .after(FoeContact::Party))
+            .add_systems(Update, start_scripted_battles.after(run_event).after(run_dialogue))
+            .add_systems(Update, danger_hud_update);
+    }
+}
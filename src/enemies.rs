@@ -0,0 +1,218 @@
+// Roaming enemy parties that are visible on the map (Think FOEs from Etrian Odyssey)
+// Each one is an entity with Party + Position + Foe, moves during TurnPhase::Enemy, and starts a battle on contact
+// Placed through the map file, the same way tile events are
+
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{party::{Party, PlayerParty}, Position};
+use crate::minimap::*;
+use crate::resources::*;
+use crate::turn::TurnPhase;
+
+// How the foe moves when it isn't chasing anyone
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum FoeBehaviour {
+    Stationary,
+    Patrol {
+        route: Vec<Position>,  // Waypoints, walked in order and looped
+        #[serde(default)]
+        next: usize,
+    },
+    Wander,
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct Foe {
+    pub behaviour: FoeBehaviour,
+    #[serde(default)]
+    pub chases: bool,   // Breaks off to chase the party once it's in sight
+    #[serde(default = "default_sight")]
+    pub sight: i32,     // How many tiles it can see down a straight corridor
+    #[serde(default)]
+    pub encounter: Option<String>,  // Enemy group fought on contact
+}
+
+fn default_sight() -> i32 { 4 }
+
+// Serialized form stored in the SavedMap
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlacedFoe {
+    pub name: String,
+    pub pos: Position,
+    #[serde(flatten)]
+    pub foe: Foe,
+}
+
+const FOE_COLOR: Color = Color::CRIMSON;
+const WANDER_IDLE_CHANCE: i32 = 25;
+
+// Spawns the map's foes, drawn on the minimap layer
+pub fn spawn_foes(commands: &mut Commands, mg: &MapGrid, foes: &[PlacedFoe]) {
+    for placed in foes {
+        let world = grid_to_world(mg, &placed.pos);
+        commands.spawn((
+            SpriteBundle{
+                sprite: Sprite { color: FOE_COLOR, custom_size: (Some(Vec2::new(1.0,1.0))), ..Default::default() },
+                visibility: Visibility::Visible,
+                transform: Transform {
+                    translation: world.extend(8.0),
+                    scale: Vec3::new(mg.zoom / 2., mg.zoom / 2., 0.),
+                    ..default()
+                },
+                ..Default::default()
+            },
+            RenderLayers::layer(2),
+            Party::new(&placed.name),
+            placed.pos.clone(),
+            placed.foe.clone(),
+        ));
+    }
+}
+
+pub fn collect_foes(query: &Query<(&Party, &Position, &Foe)>) -> Vec<PlacedFoe> {
+    query.iter()
+        .map(|(party, pos, foe)| PlacedFoe { name: party.name.clone(), pos: pos.clone(), foe: foe.clone() })
+        .collect()
+}
+
+// Numpad direction (2468) and the step it takes - matches MapGrid::validate_move
+const DIRECTIONS: [(i32, i32, i32); 4] = [(2, 0, -1), (4, -1, 0), (6, 1, 0), (8, 0, 1)];
+
+fn dir_to(dx: i32, dy: i32) -> Option<i32> {
+    DIRECTIONS.iter().find(|(_, x, y)| *x == dx && *y == dy).map(|(d, _, _)| *d)
+}
+
+// Can `from` see `to` - straight line along a row/column, within range, with no walls in between
+pub fn in_sight(mg: &MapGrid, from: &Position, to: &Position, range: i32) -> bool {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    if (dx != 0 && dy != 0) || dx.abs() + dy.abs() > range {
+        return false;
+    }
+    let Some(dir) = dir_to(dx.signum(), dy.signum()) else { return dx == 0 && dy == 0 };
+    let mut cur = from.clone();
+    while cur != *to {
+        if !mg.validate_move(&cur, dir).unwrap_or(false) {
+            return false;
+        }
+        cur.x += dx.signum();
+        cur.y += dy.signum();
+    }
+    true
+}
+
+// Picks a valid step that closes the distance to `target` - tries the longer axis first
+fn step_toward(mg: &MapGrid, from: &Position, target: &Position) -> Option<(i32, i32)> {
+    let (dx, dy) = (target.x - from.x, target.y - from.y);
+    let mut options = vec![(dx.signum(), 0), (0, dy.signum())];
+    if dy.abs() > dx.abs() {
+        options.reverse();
+    }
+    options.into_iter()
+        .filter(|(x, y)| *x != 0 || *y != 0)
+        .find(|(x, y)| dir_to(*x, *y).map(|d| mg.validate_move(from, d).unwrap_or(false)).unwrap_or(false))
+}
+
+// Works out where a foe wants to step this turn (None = stays put)
+pub fn plan_foe_step(mg: &MapGrid, foe: &mut Foe, pos: &Position, party: &Position, rng: &mut GameRng) -> Option<(i32, i32)> {
+    if foe.chases && in_sight(mg, pos, party, foe.sight) {
+        return step_toward(mg, pos, party);
+    }
+    match &mut foe.behaviour {
+        FoeBehaviour::Stationary => None,
+        FoeBehaviour::Patrol { route, next } => {
+            if route.is_empty() {
+                return None;
+            }
+            if route[*next % route.len()] == *pos {
+                *next = (*next + 1) % route.len();
+            }
+            step_toward(mg, pos, &route[*next % route.len()])
+        }
+        FoeBehaviour::Wander => {
+            if rng.roll(WANDER_IDLE_CHANCE) {
+                return None;
+            }
+            let (dir, x, y) = DIRECTIONS[rng.range(0, 4) as usize];
+            if mg.validate_move(pos, dir).unwrap_or(false) { Some((x, y)) } else { None }
+        }
+    }
+}
+
+// TurnPhase::Enemy - every foe takes one step
+pub fn move_foes(
+    mut foes: Query<(&mut Foe, &mut Position), Without<PlayerParty>>,
+    party: Query<&Position, With<PlayerParty>>,
+    mg: Res<MapGrid>,
+    mut rng: ResMut<GameRng>,
+) {
+    let Ok(party_pos) = party.get_single() else { return };
+    let mut taken: Vec<Position> = foes.iter().map(|(_, pos)| pos.clone()).collect();
+
+    for (mut foe, mut pos) in foes.iter_mut() {
+        let Some((dx, dy)) = plan_foe_step(&mg, &mut foe, &pos, party_pos, &mut rng) else { continue };
+        let dest = Position { x: pos.x + dx, y: pos.y + dy, z: pos.z };
+        // Foes don't stack on each other
+        if taken.contains(&dest) {
+            continue;
+        }
+        taken.retain(|p| *p != *pos);
+        taken.push(dest.clone());
+        *pos = dest;
+    }
+}
+
+// Runs on the player's and enemies' phases - sharing a tile with a foe starts the fight
+pub fn check_foe_contact(
+    mut commands: Commands,
+    foes: Query<(Entity, &Position, &Foe), Without<PlayerParty>>,
+    party: Query<&Position, With<PlayerParty>>,
+    mut next_turn: ResMut<NextState<TurnState>>,
+    mut next_combat: ResMut<NextState<CombatState>>,
+) {
+    let Ok(party_pos) = party.get_single() else { return };
+    if let Some((entity, _, foe)) = foes.iter().find(|(_, pos, _)| *pos == party_pos) {
+        commands.insert_resource(PendingBattle { encounter: foe.encounter.clone(), foe: Some(entity) });
+        next_turn.set(TurnState::EnterCombat);
+        next_combat.set(CombatState::EnteredCombat);
+    }
+}
+
+// Keeps the minimap sprites lined up with the grid (Also catches zoom changes)
+pub fn sync_foe_transforms(
+    mut foes: Query<(&Position, &mut Transform), With<Foe>>,
+    mg: Res<MapGrid>,
+) {
+    for (pos, mut transform) in foes.iter_mut() {
+        let world = grid_to_world(&mg, pos);
+        transform.translation.x = world.x;
+        transform.translation.y = world.y;
+        transform.scale = Vec3::new(mg.zoom / 2., mg.zoom / 2., 0.);
+    }
+}
+
+pub fn unload_foes(commands: &mut Commands, foes: &Query<Entity, With<Foe>>) {
+    for entity in foes.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// The two contact checks - order against these rather than check_foe_contact itself,
+// since Bevy can't order against a system that's registered more than once
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FoeContact {
+    Party,  // After the party moves (TurnPhase::Player)
+    Foes,   // After the foes move (TurnPhase::Enemy)
+}
+
+pub struct EnemyPlugin;
+
+impl Plugin for EnemyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, check_foe_contact.in_set(TurnPhase::Player).in_set(FoeContact::Party))
+            .add_systems(Update, (move_foes, check_foe_contact.in_set(FoeContact::Foes)).chain().in_set(TurnPhase::Enemy))
+            .add_systems(Update, sync_foe_transforms);
+    }
+}
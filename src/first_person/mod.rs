@@ -0,0 +1,112 @@
+// First-person dungeon view - wireframe walls drawn with gizmos on the main camera
+// What's visible comes from visibility.rs, this file only projects the faces onto the screen
+// The minimap camera only renders RenderLayers 2, so the gizmos stay out of it
+
+use bevy::prelude::*;
+
+use crate::components::{party::PlayerParty, Facing, Position};
+use crate::minimap::MapGrid;
+
+pub mod visibility;
+pub use visibility::*;
+
+const VIEW_CENTER: Vec2 = Vec2::new(0., 0.);
+const VIEW_SIZE: Vec2 = Vec2::new(480., 320.);
+const FOCAL: f32 = 200.;       // Screen size of a 1x1 wall face one cell away
+const NEAR_PLANE: f32 = 0.1;   // Side walls of the party's own cell get cut off here instead of running to infinity
+const WALL_COLOR: Color = Color::WHITE;
+const FRAME_COLOR: Color = Color::GRAY;
+
+#[derive(Resource)]
+pub struct FirstPersonView {
+    pub enabled: bool,
+    pub depth: i32,
+}
+
+impl Default for FirstPersonView {
+    fn default() -> Self {
+        FirstPersonView { enabled: true, depth: VIEW_DEPTH }
+    }
+}
+
+// Wall corners in view space - x is across (Cell centres on whole numbers), y is up, z is distance from the eye
+// The eye sits at the back of the party's cell, so cell `depth` runs from z = depth to z = depth + 1
+fn face_corners(face: &WallFace) -> [Vec3; 4] {
+    let l = face.lateral as f32;
+    let d = face.depth as f32;
+    let (x1, z1, x2, z2) = match face.side {
+        FaceSide::Front => (l - 0.5, d + 1., l + 0.5, d + 1.),
+        FaceSide::Left => (l - 0.5, d.max(NEAR_PLANE), l - 0.5, d + 1.),
+        FaceSide::Right => (l + 0.5, d.max(NEAR_PLANE), l + 0.5, d + 1.),
+    };
+    [Vec3::new(x1, -0.5, z1), Vec3::new(x2, -0.5, z2), Vec3::new(x2, 0.5, z2), Vec3::new(x1, 0.5, z1)]
+}
+
+fn project(p: Vec3) -> Vec2 {
+    VIEW_CENTER + Vec2::new(p.x, p.y) / p.z * FOCAL
+}
+
+// Liang-Barsky - trims the line to the view frame, None if it's entirely outside
+fn clip_line(a: Vec2, b: Vec2, min: Vec2, max: Vec2) -> Option<(Vec2, Vec2)> {
+    let d = b - a;
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (p, q) in [(-d.x, a.x - min.x), (d.x, max.x - a.x), (-d.y, a.y - min.y), (d.y, max.y - a.y)] {
+        if p == 0. {
+            if q < 0. {
+                return None;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0. {
+            t0 = t0.max(t);
+        } else {
+            t1 = t1.min(t);
+        }
+        if t0 > t1 {
+            return None;
+        }
+    }
+    Some((a + d * t0, a + d * t1))
+}
+
+pub fn draw_first_person(
+    mut gizmos: Gizmos,
+    view: Res<FirstPersonView>,
+    mg: Option<Res<MapGrid>>,
+    party: Query<(&Position, &Facing), With<PlayerParty>>,
+) {
+    if !view.enabled {
+        return;
+    }
+    let (Some(mg), Ok((pos, facing))) = (mg, party.get_single()) else { return };
+    let min = VIEW_CENTER - VIEW_SIZE / 2.;
+    let max = VIEW_CENTER + VIEW_SIZE / 2.;
+    gizmos.rect_2d(VIEW_CENTER, 0., VIEW_SIZE, FRAME_COLOR);
+
+    for face in visible_faces(&mg, pos, *facing, view.depth) {
+        let corners = face_corners(&face).map(project);
+        for i in 0..4 {
+            if let Some((a, b)) = clip_line(corners[i], corners[(i + 1) % 4], min, max) {
+                gizmos.line_2d(a, b, WALL_COLOR);
+            }
+        }
+    }
+}
+
+// V toggles the view (The minimap still works without it)
+pub fn first_person_toggle(input: Res<Input<KeyCode>>, mut view: ResMut<FirstPersonView>) {
+    if input.just_pressed(KeyCode::V) {
+        view.enabled = !view.enabled;
+    }
+}
+
+pub struct FirstPersonPlugin;
+
+impl Plugin for FirstPersonPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<FirstPersonView>()
+            .add_systems(Update, (first_person_toggle, draw_first_person).chain());
+    }
+}
@@ -0,0 +1,194 @@
+// Works out which wall faces the party can see from where they're standing - no drawing in here
+// Everything is in view space: depth counts cells straight ahead (0 is the party's own cell),
+// lateral counts cells to the side (Negative is left, positive is right)
+//
+// A cell is visible if it can be reached from the party's cell by stepping forward or sideways (Away from the centre)
+// without going through a wall, and it's inside the view cone (|lateral| <= depth + 1)
+// That's the same rough line of sight old blobbers use - good enough that walls never show through each other
+
+use std::collections::BTreeSet;
+
+use crate::components::{Facing, Position};
+use crate::minimap::MapGrid;
+
+pub const VIEW_DEPTH: i32 = 4;  // Cells drawn straight ahead, the party's own included
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaceSide {
+    Front,  // The far wall of the cell, facing the party
+    Left,   // Runs away from the party down the left of the cell
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WallFace {
+    pub depth: i32,
+    pub lateral: i32,
+    pub side: FaceSide,
+}
+
+// Grid cell at a view space offset
+pub fn view_to_grid(pos: &Position, facing: Facing, depth: i32, lateral: i32) -> (i32, i32) {
+    let (fx, fy) = facing.delta();
+    let (rx, ry) = facing.turn_right().delta();
+    (pos.x + fx * depth + rx * lateral, pos.y + fy * depth + ry * lateral)
+}
+
+// Wall (Or map edge) on the given side of the cell - validate_move already treats both the same way
+fn blocked(mg: &MapGrid, x: i32, y: i32, dir: Facing) -> bool {
+    !mg.validate_move(&Position { x, y, z: 0 }, dir.numpad()).unwrap_or(false)
+}
+
+// Cells the party can see, as (depth, lateral)
+pub fn visible_cells(mg: &MapGrid, pos: &Position, facing: Facing, max_depth: i32) -> BTreeSet<(i32, i32)> {
+    let mut visible = BTreeSet::new();
+    if !mg.in_bounds(pos.x, pos.y) {
+        return visible;
+    }
+    let cell = |d: i32, l: i32| view_to_grid(pos, facing, d, l);
+    visible.insert((0, 0));
+
+    for d in 0..max_depth {
+        let reach = d + 1;
+        // Straight on from the row behind
+        if d > 0 {
+            for l in -reach..=reach {
+                let (x, y) = cell(d - 1, l);
+                if visible.contains(&(d - 1, l)) && !blocked(mg, x, y, facing) {
+                    visible.insert((d, l));
+                }
+            }
+        }
+        // Then out to either side through any openings along the row
+        for (step, dir) in [(1, facing.turn_right()), (-1, facing.turn_left())] {
+            for l in (1..=reach).map(|l| l * step) {
+                let (x, y) = cell(d, l - step);
+                if visible.contains(&(d, l - step)) && !blocked(mg, x, y, dir) {
+                    visible.insert((d, l));
+                }
+            }
+        }
+    }
+    visible
+}
+
+// Every wall face in view, furthest first so anything filling them in later can just paint in order
+pub fn visible_faces(mg: &MapGrid, pos: &Position, facing: Facing, max_depth: i32) -> Vec<WallFace> {
+    let mut faces = Vec::new();
+    for (depth, lateral) in visible_cells(mg, pos, facing, max_depth).into_iter().rev() {
+        let (x, y) = view_to_grid(pos, facing, depth, lateral);
+        if blocked(mg, x, y, facing) {
+            faces.push(WallFace { depth, lateral, side: FaceSide::Front });
+        }
+        // Only the sides facing back towards the centre can be seen
+        if lateral <= 0 && blocked(mg, x, y, facing.turn_left()) {
+            faces.push(WallFace { depth, lateral, side: FaceSide::Left });
+        }
+        if lateral >= 0 && blocked(mg, x, y, facing.turn_right()) {
+            faces.push(WallFace { depth, lateral, side: FaceSide::Right });
+        }
+    }
+    faces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Puts a wall on one side of a cell, using the corner points add_walls expects
+    fn wall(mg: &mut MapGrid, x: i32, y: i32, side: Facing) {
+        match side {
+            Facing::North => mg.add_walls(x, y + 1, x + 1, y + 1),
+            Facing::East => mg.add_walls(x + 1, y, x + 1, y + 1),
+            Facing::South => mg.add_walls(x, y, x + 1, y),
+            Facing::West => mg.add_walls(x, y, x, y + 1),
+        }
+    }
+
+    // Corridor running north up the middle column of a 3 wide map
+    fn corridor(length: i32) -> MapGrid {
+        let mut mg = MapGrid::new(3, length);
+        for y in 0..length {
+            wall(&mut mg, 1, y, Facing::West);
+            wall(&mut mg, 1, y, Facing::East);
+        }
+        mg
+    }
+
+    fn at(x: i32, y: i32) -> Position {
+        Position { x, y, z: 0 }
+    }
+
+    #[test]
+    fn straight_corridor() {
+        let mg = corridor(8);
+        let cells = visible_cells(&mg, &at(1, 0), Facing::North, VIEW_DEPTH);
+        assert_eq!(cells, BTreeSet::from([(0, 0), (1, 0), (2, 0), (3, 0)]));
+
+        let faces = visible_faces(&mg, &at(1, 0), Facing::North, VIEW_DEPTH);
+        assert_eq!(faces.len(), 8);
+        assert!(faces.iter().all(|f| f.side != FaceSide::Front));
+        assert_eq!(faces[0].depth, 3);
+        assert_eq!(faces.last().unwrap().depth, 0);
+    }
+
+    #[test]
+    fn turned_around_in_a_corridor() {
+        let mg = corridor(8);
+        let cells = visible_cells(&mg, &at(1, 7), Facing::South, VIEW_DEPTH);
+        assert_eq!(cells, BTreeSet::from([(0, 0), (1, 0), (2, 0), (3, 0)]));
+        assert_eq!(view_to_grid(&at(1, 7), Facing::South, 3, 0), (1, 4));
+        // Looking across the corridor there's a wall right in front, the corridor only runs off to either side
+        let cells = visible_cells(&mg, &at(1, 3), Facing::East, VIEW_DEPTH);
+        assert_eq!(cells, BTreeSet::from([(0, -1), (0, 0), (0, 1)]));
+    }
+
+    #[test]
+    fn side_opening() {
+        let mut mg = corridor(8);
+        mg.remove_walls(2, 2, 2, 3);
+        let cells = visible_cells(&mg, &at(1, 0), Facing::North, VIEW_DEPTH);
+        // Through the gap on the right at depth 2 and on up the open column past it, but not past the map edge
+        assert!(cells.contains(&(2, 1)));
+        assert!(cells.contains(&(3, 1)));
+        assert!(!cells.contains(&(2, 2)));
+        assert!(!cells.contains(&(2, -1)));
+        assert!(!cells.contains(&(1, 1)));
+
+        let faces = visible_faces(&mg, &at(1, 0), Facing::North, VIEW_DEPTH);
+        assert!(!faces.contains(&WallFace { depth: 2, lateral: 0, side: FaceSide::Right }));
+        assert!(faces.contains(&WallFace { depth: 2, lateral: 0, side: FaceSide::Left }));
+        // The cell through the gap shows its far wall and the map edge beyond it
+        assert!(faces.contains(&WallFace { depth: 2, lateral: 1, side: FaceSide::Right }));
+    }
+
+    #[test]
+    fn wall_blocks_sight() {
+        let mut mg = MapGrid::new(3, 6);
+        wall(&mut mg, 1, 1, Facing::North);
+        let cells = visible_cells(&mg, &at(1, 0), Facing::North, VIEW_DEPTH);
+        assert!(cells.contains(&(1, 0)));
+        assert!(!cells.contains(&(2, 0)));
+        assert!(!cells.contains(&(3, 0)));
+        // Still visible round the side
+        assert!(cells.contains(&(2, -1)));
+        assert!(cells.contains(&(2, 1)));
+
+        let faces = visible_faces(&mg, &at(1, 0), Facing::North, VIEW_DEPTH);
+        assert!(faces.contains(&WallFace { depth: 1, lateral: 0, side: FaceSide::Front }));
+    }
+
+    #[test]
+    fn map_edge_and_out_of_bounds() {
+        let mg = corridor(3);
+        let cells = visible_cells(&mg, &at(1, 0), Facing::North, VIEW_DEPTH);
+        assert_eq!(cells, BTreeSet::from([(0, 0), (1, 0), (2, 0)]));
+        // The top of the map counts as a wall
+        let faces = visible_faces(&mg, &at(1, 0), Facing::North, VIEW_DEPTH);
+        assert_eq!(faces[0], WallFace { depth: 2, lateral: 0, side: FaceSide::Front });
+
+        assert!(visible_cells(&mg, &at(-1, 0), Facing::North, VIEW_DEPTH).is_empty());
+        assert!(visible_cells(&mg, &at(1, 3), Facing::North, VIEW_DEPTH).is_empty());
+        assert!(visible_faces(&mg, &at(5, 5), Facing::East, VIEW_DEPTH).is_empty());
+    }
+}
@@ -0,0 +1,248 @@
+// Items - defined in assets/data/items.json, carried by the party in a shared Inventory
+// Consumables run their effects through the same interpreter as skills (apply_effects), in the field or in combat
+// Key items never get used up - locked doors and event scripts just check whether the party is carrying them
+
+use std::collections::HashMap;
+use std::fs::File;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{character::Character, equipment::EquipInfo, party::PlayerParty};
+use crate::resources::GameRng;
+use crate::scripting::{ScriptAction, ScriptActionEvent};
+use crate::skills::*;
+
+pub const ITEM_FILE: &str = "assets/data/items.json";
+
+pub const MAX_INVENTORY_SLOTS: usize = 60;  // Distinct stacks the bag can hold
+const DEFAULT_MAX_STACK: u32 = 99;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ItemKind {
+    #[default]
+    Consumable,
+    Key,        // Only ever one of each, can't be used up or thrown away
+    Equipment,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ItemDef {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub kind: ItemKind,
+    #[serde(default)]
+    pub value: u32,  // Shop price
+    #[serde(default = "default_max_stack")]
+    pub max_stack: u32,
+    #[serde(default = "default_item_usable")]
+    pub usable: Usable,
+    #[serde(default = "default_item_target")]
+    pub target: Targeting,
+    #[serde(default)]
+    pub effects: Vec<SkillEffect>,
+    #[serde(default)]
+    pub equip: Option<EquipInfo>,  // Slot, class restrictions and modifiers for Equipment
+}
+
+fn default_max_stack() -> u32 { DEFAULT_MAX_STACK }
+fn default_item_usable() -> Usable { Usable::Both }
+fn default_item_target() -> Targeting { Targeting { side: TargetSide::Ally, scope: TargetScope::Single } }
+
+impl ItemDef {
+    // Only consumables with something to do can be used
+    pub fn is_usable(&self) -> bool {
+        self.kind == ItemKind::Consumable && !self.effects.is_empty()
+    }
+
+    pub fn stack_limit(&self) -> u32 {
+        if self.kind == ItemKind::Key { 1 } else { self.max_stack.max(1) }
+    }
+
+    pub fn targets_dead(&self) -> bool {
+        revives(&self.effects)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ItemLibrary {
+    pub items: HashMap<String, ItemDef>,
+}
+
+impl ItemLibrary {
+    pub fn load_from_file(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Unable to open {}: {}", path, e))?;
+        let items: Vec<ItemDef> = serde_json::from_reader(file)
+            .map_err(|e| format!("Unable to parse {}: {}", path, e))?;
+        Ok(ItemLibrary { items: items.into_iter().map(|i| (i.id.clone(), i)).collect() })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ItemDef> {
+        self.items.get(id)
+    }
+
+    // Falls back to the id for anything missing from the library
+    pub fn name<'a>(&'a self, id: &'a str) -> &'a str {
+        self.get(id).map(|i| i.name.as_str()).unwrap_or(id)
+    }
+}
+
+pub fn load_item_library(mut commands: Commands) {
+    match ItemLibrary::load_from_file(ITEM_FILE) {
+        Ok(lib) => commands.insert_resource(lib),
+        Err(e) => {
+            println!("{} - no items available", e);
+            commands.insert_resource(ItemLibrary::default());
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ItemStack {
+    pub item: String,
+    pub qty: u32,
+}
+
+// The party's bag - lives on the party entity, stacks are kept in the order they were picked up
+#[derive(Component, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Inventory {
+    pub items: Vec<ItemStack>,
+}
+
+impl Inventory {
+    pub fn count(&self, item: &str) -> u32 {
+        self.items.iter().find(|s| s.item == item).map(|s| s.qty).unwrap_or(0)
+    }
+
+    pub fn has(&self, item: &str) -> bool {
+        self.count(item) > 0
+    }
+
+    // Returns how many actually went in - anything past the stack limit (Or a full bag) is left behind
+    pub fn add(&mut self, item: &ItemDef, qty: u32) -> u32 {
+        let limit = item.stack_limit();
+        if let Some(stack) = self.items.iter_mut().find(|s| s.item == item.id) {
+            let added = qty.min(limit - stack.qty.min(limit));
+            stack.qty += added;
+            return added;
+        }
+        if self.items.len() >= MAX_INVENTORY_SLOTS {
+            return 0;
+        }
+        let added = qty.min(limit);
+        if added > 0 {
+            self.items.push(ItemStack { item: item.id.clone(), qty: added });
+        }
+        added
+    }
+
+    // Puts back something that came out of the bag (Like unequipped gear) - it fit before, so no limit checks
+    pub fn return_item(&mut self, item: &str) {
+        match self.items.iter_mut().find(|s| s.item == item) {
+            Some(stack) => stack.qty += 1,
+            None => self.items.push(ItemStack { item: item.to_string(), qty: 1 }),
+        }
+    }
+
+    pub fn remove(&mut self, item: &str, qty: u32) -> Result<(), String> {
+        let have = self.count(item);
+        if have < qty {
+            return Err(format!("Only have {} of {}", have, item));
+        }
+        self.items.retain_mut(|s| {
+            if s.item == item {
+                s.qty -= qty;
+            }
+            s.qty > 0
+        });
+        Ok(())
+    }
+
+    // Consumables usable in the current context, in bag order
+    pub fn usable<'a>(&self, items: &'a ItemLibrary, in_combat: bool) -> Vec<(&'a ItemDef, u32)> {
+        self.items.iter()
+            .filter_map(|s| items.get(&s.item).map(|def| (def, s.qty)))
+            .filter(|(def, _)| def.is_usable() && if in_combat { def.usable.in_combat() } else { def.usable.in_field() })
+            .collect()
+    }
+}
+
+// Items hit for a set amount rather than scaling off whoever uses them,
+// so effect power on an item reads as the raw number (Heal power 30 -> 30 HP)
+pub fn item_user(item: &ItemDef) -> SkillUser {
+    SkillUser { name: item.name.clone(), attack: 100, magic: 100, healing: 100 }
+}
+
+// Field use - same shape as use_skill_in_field, but the cost comes out of the bag instead of TP
+pub fn use_item_in_field(
+    inventory: &mut Inventory,
+    members: &mut [Character],
+    item: &ItemDef,
+    targets: &[usize],
+    rng: &mut GameRng,
+) -> Result<Vec<String>, String> {
+    if !item.is_usable() || !item.usable.in_field() {
+        return Err(format!("{} can't be used here", item.name));
+    }
+    if item.target.side == TargetSide::Enemy {
+        return Err(format!("{} needs an enemy to target", item.name));
+    }
+    inventory.remove(&item.id, 1).map_err(|_| format!("No {} left", item.name))?;
+
+    let user = item_user(item);
+    let mut log = vec![format!("Used a {}", item.name)];
+    for slot in targets {
+        let Some(target) = members.get_mut(*slot) else { continue };
+        log.extend(apply_effects(&item.effects, 1, &user, target, rng));
+    }
+    Ok(log)
+}
+
+// What a fresh party starts out carrying
+const STARTING_ITEMS: [(&str, u32); 4] = [("medica", 3), ("theriaca", 1), ("short_sword", 1), ("leather_armor", 2)];
+
+pub fn starting_inventory(items: &ItemLibrary) -> Inventory {
+    let mut inventory = Inventory::default();
+    for (id, qty) in STARTING_ITEMS {
+        if let Some(item) = items.get(id) {
+            inventory.add(item, qty);
+        }
+    }
+    inventory
+}
+
+// Picks up GiveItem requests from events, dialogue and breakables
+pub fn receive_items(
+    mut actions: EventReader<ScriptActionEvent>,
+    mut party: Query<&mut Inventory, With<PlayerParty>>,
+    items: Res<ItemLibrary>,
+) {
+    for ScriptActionEvent(action) in actions.read() {
+        let ScriptAction::GiveItem { item, qty } = action else { continue };
+        let Ok(mut inventory) = party.get_single_mut() else { return };
+        let Some(def) = items.get(item) else {
+            println!("Tried to give unknown item {}", item);
+            continue;
+        };
+        let added = inventory.add(def, *qty);
+        if added > 0 {
+            println!("Obtained {} x{}", def.name, added);
+        }
+        if added < *qty {
+            println!("No room for {} more {}", qty - added, def.name);
+        }
+    }
+}
+
+pub struct ItemPlugin;
+
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, load_item_library)
+            .add_systems(Update, receive_items);
+    }
+}
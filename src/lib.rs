@@ -1,3 +1,15 @@
+pub mod autoheal;
+pub mod combat;
 pub mod components;
+pub mod dialogue;
+pub mod enemies;
+pub mod encounters;
+pub mod first_person;
+pub mod items;
 pub mod minimap;
-pub mod resources;
\ No newline at end of file
+pub mod resources;
+pub mod save;
+pub mod scripting;
+pub mod skills;
+pub mod textbox;
+pub mod turn;
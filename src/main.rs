@@ -2,19 +2,43 @@ use bevy::prelude::*;
 use bevy_ecs_ldtk::prelude::*;
 
 // mod map;
+mod autoheal;
+mod combat;
 mod components;
+mod dialogue;
+mod enemies;
+mod encounters;
+mod first_person;
+mod items;
 mod resources;
 // mod map_pipeline;
 mod minimap;
+mod save;
+mod scripting;
+mod skills;
+mod textbox;
+mod turn;
 
 mod prelude {
     pub use bevy::prelude::*;
     pub use serde::*;
     // pub use crate::map::*;
+    pub use crate::autoheal::*;
+    pub use crate::combat::*;
     pub use crate::components::*;
+    pub use crate::dialogue::*;
+    pub use crate::enemies::*;
+    pub use crate::encounters::*;
+    pub use crate::first_person::*;
+    pub use crate::items::*;
     pub use crate::resources::*;
     // pub use crate::map_pipeline::*;
     pub use crate::minimap::*;
+    pub use crate::save::*;
+    pub use crate::scripting::*;
+    pub use crate::skills::*;
+    pub use crate::textbox::*;
+    pub use crate::turn::*;
 }
 
 use prelude::*;
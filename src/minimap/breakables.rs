@@ -0,0 +1,156 @@
+// Breakable objects - barrels, crates, cracked walls, anything that isn't alive but can still be knocked down
+// They carry a regular Health component, so they take damage and die through the same events as everything else
+// A breakable wall sits on one edge of its tile, and removes that wall from the map when it breaks
+
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{party::{Party, PlayerParty}, Died, Health, HealthEvents, Position};
+use crate::minimap::*;
+use crate::resources::*;
+use crate::scripting::{ScriptAction, ScriptActionEvent};
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+pub struct Breakable {
+    pub name: String,
+    #[serde(default)]
+    pub wall: Option<i32>,      // Numpad direction (2468) of the wall edge this breaks open, if it's a wall
+    #[serde(default)]
+    pub drops: Option<String>,  // Item handed over when it breaks
+}
+
+// Serialized form stored in the SavedMap
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlacedBreakable {
+    pub pos: Position,
+    pub hp: i32,
+    #[serde(flatten)]
+    pub breakable: Breakable,
+}
+
+const BREAKABLE_COLOR: Color = Color::rgb(0.55, 0.35, 0.15);
+
+pub fn spawn_breakables(commands: &mut Commands, mg: &MapGrid, breakables: &[PlacedBreakable]) {
+    for placed in breakables {
+        let world = grid_to_world(mg, &placed.pos);
+        commands.spawn((
+            SpriteBundle{
+                sprite: Sprite { color: BREAKABLE_COLOR, custom_size: (Some(Vec2::new(1.0,1.0))), ..Default::default() },
+                visibility: Visibility::Visible,
+                transform: Transform {
+                    translation: world.extend(7.0),
+                    scale: Vec3::new(mg.zoom / 3., mg.zoom / 3., 0.),
+                    ..default()
+                },
+                ..Default::default()
+            },
+            RenderLayers::layer(2),
+            Health::new(placed.hp),
+            placed.pos.clone(),
+            placed.breakable.clone(),
+        ));
+    }
+}
+
+pub fn collect_breakables(query: &Query<(&Position, &Health, &Breakable)>) -> Vec<PlacedBreakable> {
+    query.iter()
+        .map(|(pos, health, breakable)| PlacedBreakable { pos: pos.clone(), hp: health.hp(), breakable: breakable.clone() })
+        .collect()
+}
+
+pub fn unload_breakables(commands: &mut Commands, breakables: &Query<Entity, With<Breakable>>) {
+    for entity in breakables.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// Step from a tile through the given edge
+fn neighbour(pos: &Position, dir: i32) -> Option<Position> {
+    let (dx, dy) = match dir {
+        2 => (0, -1),
+        4 => (-1, 0),
+        6 => (1, 0),
+        8 => (0, 1),
+        _ => return None,
+    };
+    Some(Position { x: pos.x + dx, y: pos.y + dy, z: pos.z })
+}
+
+// The two grid points making up a tile's edge - same corner convention add_walls/remove_walls use
+fn wall_line(pos: &Position, dir: i32) -> Option<(i32, i32, i32, i32)> {
+    let (x, y) = (pos.x, pos.y);
+    match dir {
+        2 => Some((x, y, x + 1, y)),
+        8 => Some((x, y + 1, x + 1, y + 1)),
+        4 => Some((x, y, x, y + 1)),
+        6 => Some((x + 1, y, x + 1, y + 1)),
+        _ => None,
+    }
+}
+
+// Walls can be hit from either side, everything else needs the party on or next to it
+fn within_reach(mg: &MapGrid, party: &Position, pos: &Position, breakable: &Breakable) -> bool {
+    if party == pos {
+        return true;
+    }
+    if let Some(dir) = breakable.wall {
+        return neighbour(pos, dir).as_ref() == Some(party);
+    }
+    [2, 4, 6, 8].iter().any(|dir| {
+        neighbour(party, *dir).as_ref() == Some(pos) && mg.validate_move(party, *dir).unwrap_or(false)
+    })
+}
+
+// Strike key (F) - the party leader takes a swing at something breakable in reach, which uses up the turn
+pub fn strike_breakables(
+    input: Res<Input<KeyCode>>,
+    party: Query<(&Party, &Position), With<PlayerParty>>,
+    mut breakables: Query<(Entity, &Position, &Breakable, &mut Health), Without<PlayerParty>>,
+    mg: Res<MapGrid>,
+    mut health_events: HealthEvents,
+    mut next_turn: ResMut<NextState<TurnState>>,
+) {
+    if !input.just_pressed(KeyCode::F) {
+        return;
+    }
+    let Ok((party, party_pos)) = party.get_single() else { return };
+    let Some(leader) = party.leader() else { return };
+    let target = breakables.iter_mut()
+        .find(|(_, pos, breakable, health)| !health.is_dead() && within_reach(&mg, party_pos, pos, breakable));
+    let Some((entity, _, breakable, mut health)) = target else { return };
+
+    let taken = health_events.damage(&mut health, leader.character.attack(), entity, None);
+    println!("{} hits the {} for {} ({} HP left)", leader.name(), breakable.name, taken, health.hp());
+    next_turn.set(TurnState::PlayerTurn);
+}
+
+// Clears away anything breakable that just died - opens up walls and hands over drops
+pub fn break_on_death(
+    mut commands: Commands,
+    mut died: EventReader<Died>,
+    breakables: Query<(&Position, &Breakable)>,
+    mut mg: ResMut<MapGrid>,
+    mut wg: ResMut<WallGrid>,
+    mut actions: EventWriter<ScriptActionEvent>,
+    mut next_render: ResMut<NextState<MapBuildState>>,
+) {
+    for event in died.read() {
+        if event.member.is_some() {
+            continue;
+        }
+        let Ok((pos, breakable)) = breakables.get(event.entity) else { continue };
+        println!("The {} breaks apart!", breakable.name);
+
+        if let Some((x1, y1, x2, y2)) = breakable.wall.and_then(|dir| wall_line(pos, dir)) {
+            mg.remove_walls(x1, y1, x2, y2);
+            wg.remove_wall(x1, y1, x2, y2);
+            // Redraw the minimap so the opening shows up
+            next_render.set(MapBuildState::RenderMap);
+        }
+        if let Some(item) = &breakable.drops {
+            actions.send(ScriptActionEvent(ScriptAction::GiveItem { item: item.clone(), qty: 1 }));
+        }
+        commands.entity(event.entity).despawn_recursive();
+    }
+}
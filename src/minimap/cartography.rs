@@ -0,0 +1,356 @@
+// Cartography - the player's own hand-drawn copy of each map, Etrian Odyssey style
+// Drawn walls, floor colours and icons live here, never on the real MapGrid/WallGrid
+// The Difficulty decides whether it's used at all (Casual just shows the real map) and whether auto-map assist is allowed
+//
+// Controls (MenuState::Map, entered with T while exploring):
+//     Left click near a cell edge - toggle a wall      Left click inside a cell - paint it (Again to clear)
+//     Right click - place the selected icon (Again to clear)
+//     1-5 - pick the floor colour    I - next icon    P - toggle auto-map    T/Escape - back to exploring
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{party::PlayerParty, Facing, Position};
+use crate::minimap::*;
+use crate::resources::*;
+use crate::turn::TurnPhase;
+
+const EDGE_SNAP: f32 = 0.3;  // How close to a cell edge (In cells) a click has to be to count as drawing a wall
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FloorPaint {
+    Plain,
+    Water,
+    Damage,
+    Poison,
+    Dark,
+}
+
+pub const FLOOR_PAINTS: [FloorPaint; 5] = [FloorPaint::Plain, FloorPaint::Water, FloorPaint::Damage, FloorPaint::Poison, FloorPaint::Dark];
+
+impl FloorPaint {
+    pub fn color(self) -> Color {
+        match self {
+            FloorPaint::Plain => Color::rgba(0.9, 0.9, 0.8, 0.5),
+            FloorPaint::Water => Color::rgba(0.2, 0.4, 1.0, 0.6),
+            FloorPaint::Damage => Color::rgba(1.0, 0.2, 0.1, 0.6),
+            FloorPaint::Poison => Color::rgba(0.5, 0.9, 0.2, 0.6),
+            FloorPaint::Dark => Color::rgba(0.2, 0.1, 0.3, 0.8),
+        }
+    }
+}
+
+// One map as the player has drawn it - only the grid's walls and icons are used
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DrawnMap {
+    pub grid: MapGrid,
+    #[serde(default)]
+    pub floors: BTreeMap<i32, FloorPaint>,  // Keyed by xy_index, same as tile props
+}
+
+impl DrawnMap {
+    pub fn new(dim_x: i32, dim_y: i32) -> Self {
+        DrawnMap { grid: MapGrid::new(dim_x, dim_y), floors: BTreeMap::new() }
+    }
+
+    pub fn has_wall(&self, x: i32, y: i32, side: Facing) -> bool {
+        self.grid.in_bounds(x, y) && self.grid.tiles[self.grid.xy_index(x, y) as usize].walls[wall_slot(side)]
+    }
+
+    // Sets both sides of the edge, same as the builder does
+    pub fn set_wall(&mut self, x: i32, y: i32, side: Facing, present: bool) {
+        let (x1, y1, x2, y2) = edge_line(x, y, side);
+        if present {
+            self.grid.add_walls(x1, y1, x2, y2);
+        } else {
+            self.grid.remove_walls(x1, y1, x2, y2);
+        }
+    }
+
+    // Returns the new state of the wall
+    pub fn toggle_wall(&mut self, x: i32, y: i32, side: Facing) -> bool {
+        let present = !self.has_wall(x, y, side);
+        self.set_wall(x, y, side, present);
+        present
+    }
+
+    // Painting a cell the colour it already is clears it
+    pub fn paint(&mut self, x: i32, y: i32, paint: FloorPaint) {
+        if !self.grid.in_bounds(x, y) {
+            return;
+        }
+        let index = self.grid.xy_index(x, y);
+        if self.floors.get(&index) == Some(&paint) {
+            self.floors.remove(&index);
+        } else {
+            self.floors.insert(index, paint);
+        }
+    }
+
+    pub fn mark_icon(&mut self, x: i32, y: i32, icon: MapIcon) {
+        self.grid.toggle_icon(x, y, icon);
+    }
+
+    // Auto-map assist - copies the real walls around a cell the party is standing on, and fills in its floor
+    pub fn auto_map(&mut self, mg: &MapGrid, x: i32, y: i32) {
+        if !mg.in_bounds(x, y) || !self.grid.in_bounds(x, y) {
+            return;
+        }
+        let tile = mg.tiles[mg.xy_index(x, y) as usize];
+        for side in [Facing::North, Facing::East, Facing::South, Facing::West] {
+            if tile.walls[wall_slot(side)] {
+                self.set_wall(x, y, side, true);
+            }
+        }
+        self.floors.entry(self.grid.xy_index(x, y)).or_insert(FloorPaint::Plain);
+    }
+}
+
+// Tile.walls index for a side of the cell
+fn wall_slot(side: Facing) -> usize {
+    match side {
+        Facing::North => UP,
+        Facing::East => RIGHT,
+        Facing::South => DOWN,
+        Facing::West => LEFT,
+    }
+}
+
+// Corner coordinates of a cell edge, in the form add_walls/remove_walls take (Corner x,y is the bottom left of cell x,y)
+fn edge_line(x: i32, y: i32, side: Facing) -> (i32, i32, i32, i32) {
+    match side {
+        Facing::North => (x, y + 1, x + 1, y + 1),
+        Facing::East => (x + 1, y, x + 1, y + 1),
+        Facing::South => (x, y, x + 1, y),
+        Facing::West => (x, y, x, y + 1),
+    }
+}
+
+// Every drawn map in the game, keyed by map path - goes into save files
+#[derive(Resource, Serialize, Deserialize, Clone, Default)]
+pub struct Cartography {
+    pub maps: BTreeMap<String, DrawnMap>,
+    #[serde(default)]
+    pub auto_map: bool,
+}
+
+impl Cartography {
+    // Starts a blank sheet the first time a map is drawn on
+    pub fn map_mut(&mut self, path: &str, mg: &MapGrid) -> &mut DrawnMap {
+        self.maps.entry(path.to_string()).or_insert_with(|| DrawnMap::new(mg.dim_x, mg.dim_y))
+    }
+}
+
+// What the player currently has picked in the palette - not saved
+#[derive(Resource)]
+pub struct CartographyTool {
+    pub paint: FloorPaint,
+    pub icon: MapIcon,
+}
+
+impl Default for CartographyTool {
+    fn default() -> Self {
+        CartographyTool { paint: FloorPaint::Plain, icon: MapIcon::Door }
+    }
+}
+
+#[derive(Component)]
+pub struct CartographySprite;
+
+// Run condition - the real walls only go on the minimap when nobody is drawing their own
+pub fn cartography_off(difficulty: Res<Difficulty>) -> bool {
+    !difficulty.cartography()
+}
+
+// T while exploring opens the map for drawing
+pub fn open_cartography(
+    input: Res<Input<KeyCode>>,
+    difficulty: Res<Difficulty>,
+    mut next_gameplay: ResMut<NextState<GameplayState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    if !input.just_pressed(KeyCode::T) {
+        return;
+    }
+    if !difficulty.cartography() {
+        println!("The map draws itself on this difficulty");
+        return;
+    }
+    println!("Drawing the map - T or Escape to go back");
+    next_gameplay.set(GameplayState::Menu);
+    next_menu.set(MenuState::Map);
+}
+
+// MenuState::Map - clicks on the minimap draw on the current map's sheet
+pub fn cartography_input(
+    input: Res<Input<KeyCode>>,
+    mouse: Res<Input<MouseButton>>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    minimap_cam: Query<(&Camera, &GlobalTransform), With<MinimapCamera>>,
+    mg: Res<MapGrid>,
+    current: Option<Res<CurrentMap>>,
+    difficulty: Res<Difficulty>,
+    mut tool: ResMut<CartographyTool>,
+    mut cartography: ResMut<Cartography>,
+    mut next_gameplay: ResMut<NextState<GameplayState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    if input.any_just_pressed([KeyCode::T, KeyCode::Escape]) {
+        next_menu.set(MenuState::UpperView);
+        next_gameplay.set(GameplayState::Exploration);
+        return;
+    }
+    for (key, paint) in [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5].into_iter().zip(FLOOR_PAINTS) {
+        if input.just_pressed(key) {
+            tool.paint = paint;
+            println!("Floor colour: {:?}", paint);
+        }
+    }
+    if input.just_pressed(KeyCode::I) {
+        tool.icon = tool.icon.next();
+        println!("Icon: {:?}", tool.icon);
+    }
+    if input.just_pressed(KeyCode::P) {
+        if difficulty.allows_auto_map() {
+            cartography.auto_map = !cartography.auto_map;
+            println!("Auto-map {}", if cartography.auto_map { "on" } else { "off" });
+        } else {
+            println!("No auto-map on this difficulty");
+        }
+    }
+
+    let left = mouse.just_pressed(MouseButton::Left);
+    let right = mouse.just_pressed(MouseButton::Right);
+    if !left && !right {
+        return;
+    }
+    let Some(current) = current else { return };
+    let (Ok(window), Ok((camera, camera_transform))) = (q_window.get_single(), minimap_cam.get_single()) else { return };
+    let Some(cursor) = window.cursor_position() else { return };
+    let Some(viewport) = camera.logical_viewport_rect() else { return };
+    if !viewport.contains(cursor) {
+        return;
+    }
+    let Some(world) = camera.viewport_to_world(camera_transform, cursor - viewport.min).map(|ray| ray.origin.truncate()) else { return };
+
+    let grid = world_to_grid(&mg, world);
+    let (x, y) = (grid.x.round() as i32, grid.y.round() as i32);
+    if !mg.in_bounds(x, y) {
+        return;
+    }
+    let (fx, fy) = (grid.x - x as f32, grid.y - y as f32);
+    let sheet = cartography.map_mut(&current.path, &mg);
+
+    if right {
+        sheet.mark_icon(x, y, tool.icon);
+        return;
+    }
+    // Near an edge draws a wall, anywhere else paints the floor
+    let edge = if fx.abs() >= fy.abs() {
+        if fx > EDGE_SNAP { Some(Facing::East) } else if fx < -EDGE_SNAP { Some(Facing::West) } else { None }
+    } else {
+        if fy > EDGE_SNAP { Some(Facing::North) } else if fy < -EDGE_SNAP { Some(Facing::South) } else { None }
+    };
+    match edge {
+        Some(side) => { sheet.toggle_wall(x, y, side); }
+        None => sheet.paint(x, y, tool.paint),
+    }
+}
+
+// Fills in the party's cell as they walk, if auto-map assist is on
+pub fn auto_map_cartography(
+    party: Query<Ref<Position>, With<PlayerParty>>,
+    current: Option<Res<CurrentMap>>,
+    mg: Res<MapGrid>,
+    difficulty: Res<Difficulty>,
+    mut cartography: ResMut<Cartography>,
+) {
+    if !difficulty.cartography() || !difficulty.allows_auto_map() || !cartography.auto_map {
+        return;
+    }
+    let (Ok(pos), Some(current)) = (party.get_single(), current) else { return };
+    // Arriving through a transition bypasses change detection, so a new map counts too
+    if !pos.is_changed() && !current.is_changed() {
+        return;
+    }
+    cartography.map_mut(&current.path, &mg).auto_map(&mg, pos.x, pos.y);
+}
+
+// Redraws the player's sheet on the minimap whenever it (Or the map/zoom under it) changes
+pub fn draw_cartography(
+    mut commands: Commands,
+    sprites: Query<Entity, With<CartographySprite>>,
+    cartography: Res<Cartography>,
+    mg: Res<MapGrid>,
+    current: Option<Res<CurrentMap>>,
+    difficulty: Res<Difficulty>,
+) {
+    let Some(current) = current else { return };
+    if !cartography.is_changed() && !mg.is_changed() && !current.is_changed() && !difficulty.is_changed() {
+        return;
+    }
+    for entity in sprites.iter() {
+        commands.entity(entity).despawn();
+    }
+    if !difficulty.cartography() {
+        return;
+    }
+    let Some(sheet) = cartography.maps.get(&current.path) else { return };
+
+    let mut spawn = |center: Vec2, size: Vec2, z: f32, color: Color| {
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite { color, custom_size: Some(Vec2::new(1.0, 1.0)), ..Default::default() },
+                transform: Transform { translation: center.extend(z), scale: size.extend(1.), ..default() },
+                ..Default::default()
+            },
+            CartographySprite,
+            RenderLayers::layer(2),
+        ));
+    };
+    for y in 0..sheet.grid.dim_y {
+        for x in 0..sheet.grid.dim_x {
+            let index = sheet.grid.xy_index(x, y);
+            let center = grid_to_world(&mg, &Position { x, y, z: 0 });
+            if let Some(paint) = sheet.floors.get(&index) {
+                spawn(center, Vec2::splat(mg.zoom - 1.), 0.5, paint.color());
+            }
+            // Each edge is drawn by the cell below/left of it, so only the bottom and left borders need their own
+            for side in [Facing::North, Facing::East, Facing::South, Facing::West] {
+                let owned = match side {
+                    Facing::North | Facing::East => true,
+                    Facing::South => y == 0,
+                    Facing::West => x == 0,
+                };
+                if !owned || !sheet.has_wall(x, y, side) {
+                    continue;
+                }
+                let (dx, dy) = side.delta();
+                let offset = Vec2::new(dx as f32, dy as f32) * mg.zoom / 2.;
+                let size = if dx == 0 { Vec2::new(mg.zoom, 1.5) } else { Vec2::new(1.5, mg.zoom) };
+                spawn(center + offset, size, 1., Color::ANTIQUE_WHITE);
+            }
+        }
+    }
+    for (index, icon) in sheet.grid.icons.iter() {
+        let (x, y) = (index % sheet.grid.dim_x, index / sheet.grid.dim_x);
+        commands.spawn((map_icon_sprite(&mg, x, y, *icon), CartographySprite, RenderLayers::layer(2)));
+    }
+}
+
+pub struct CartographyPlugin;
+
+impl Plugin for CartographyPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<Cartography>()
+            .init_resource::<CartographyTool>()
+            .add_systems(Update, open_cartography.in_set(TurnPhase::Input))
+            .add_systems(Update, cartography_input.run_if(in_state(GameplayState::Menu)).run_if(in_state(MenuState::Map)))
+            .add_systems(Update, (auto_map_cartography, draw_cartography).chain());
+    }
+}
@@ -5,6 +5,7 @@
 
 use bevy::prelude::*;
 use bevy::ecs::world;
+use serde::{Deserialize, Serialize};
 
 use crate::{components::*, minimap::*, resources::*, };
 
@@ -19,6 +20,105 @@ pub struct MapWallSprite;
 #[derive(Component)]
 pub struct MapCellIcon;
 
+// Symbols that can be marked on a cell - shared by the builder and the player's own map
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapIcon {
+    Door,
+    Stairs,
+    Chest,
+    Event,
+    GatheringPoint,
+    Note,
+    Danger,
+}
+
+pub const MAP_ICONS: [MapIcon; 7] = [
+    MapIcon::Door, MapIcon::Stairs, MapIcon::Chest, MapIcon::Event,
+    MapIcon::GatheringPoint, MapIcon::Note, MapIcon::Danger,
+];
+
+impl MapIcon {
+    // Placeholder colours until there are proper icon sprites
+    pub fn color(self) -> Color {
+        match self {
+            MapIcon::Door => Color::ORANGE,
+            MapIcon::Stairs => Color::WHITE,
+            MapIcon::Chest => Color::GOLD,
+            MapIcon::Event => Color::FUCHSIA,
+            MapIcon::GatheringPoint => Color::LIME_GREEN,
+            MapIcon::Note => Color::ALICE_BLUE,
+            MapIcon::Danger => Color::CRIMSON,
+        }
+    }
+
+    // Cycles through the palette
+    pub fn next(self) -> MapIcon {
+        let i = MAP_ICONS.iter().position(|icon| *icon == self).unwrap_or(0);
+        MAP_ICONS[(i + 1) % MAP_ICONS.len()]
+    }
+}
+
+// Icon accessors, keyed by xy_index like the tile props
+impl MapGrid {
+    pub fn icon(&self, x: i32, y: i32) -> Option<MapIcon> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+        self.icons.get(&self.xy_index(x, y)).copied()
+    }
+
+    // Marking a cell with the icon it already has clears it - returns what the cell ends up with
+    pub fn toggle_icon(&mut self, x: i32, y: i32, icon: MapIcon) -> Option<MapIcon> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+        let index = self.xy_index(x, y);
+        if self.icons.get(&index) == Some(&icon) {
+            self.icons.remove(&index);
+            None
+        } else {
+            self.icons.insert(index, icon);
+            Some(icon)
+        }
+    }
+}
+
+// Icon sprite sized off the current zoom, so it's redrawn rather than scaled when the minimap zoom changes
+pub fn map_icon_sprite(mg: &MapGrid, x: i32, y: i32, icon: MapIcon) -> SpriteBundle {
+    SpriteBundle{
+        sprite: Sprite { color: icon.color(), custom_size: (Some(Vec2::new(1.0,1.0))), ..Default::default() },
+        transform: Transform {
+            translation: grid_to_world(mg, &Position{ x, y, z: 0 }).extend(3.0),
+            scale: Vec3::new(mg.zoom / 2., mg.zoom / 2., 1.),
+            ..default()
+        },
+        ..Default::default()
+    }
+}
+
+// Renders the map's icons - redrawn whenever the MapGrid changes (Loads, edits, minimap zoom)
+// Left off while the player draws their own map, since the icons would give the layout away (See cartography.rs)
+pub fn draw_map_icons(
+    mut commands: Commands,
+    icons: Query<Entity, With<MapCellIcon>>,
+    mg: Res<MapGrid>,
+    difficulty: Option<Res<Difficulty>>,
+) {
+    if !mg.is_changed() && !difficulty.as_ref().is_some_and(|d| d.is_changed()) {
+        return;
+    }
+    for entity in icons.iter() {
+        commands.entity(entity).despawn();
+    }
+    if difficulty.is_some_and(|d| d.cartography()) {
+        return;
+    }
+    for (index, icon) in mg.icons.iter() {
+        let (x, y) = (index % mg.dim_x, index / mg.dim_x);
+        commands.spawn((map_icon_sprite(&mg, x, y, *icon), MapCellIcon, RenderLayers::layer(2)));
+    }
+}
+
 #[derive(Component)]
 pub struct SelectedOption;
 
@@ -91,6 +191,7 @@ pub fn draw_wall(mut commands: Commands, mw: Res<WallGrid>, mg: Res<MapGrid>){
                     },
                     ..Default::default()
                 }, 
+                MapWallSprite, 
                 RenderLayers::layer(2),
                 ));
             }
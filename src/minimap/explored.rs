@@ -0,0 +1,44 @@
+// Explored cells - which tiles the party has actually set foot on, per map
+// Kept apart from the MapGrid so the real map data never changes just from walking around
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{party::PlayerParty, Position};
+use crate::minimap::*;
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Explored {
+    pub maps: BTreeMap<String, BTreeSet<i32>>,  // Map path -> xy_index of every visited cell
+}
+
+impl Explored {
+    // Returns true if this is the first visit
+    pub fn mark(&mut self, map: &str, mg: &MapGrid, pos: &Position) -> bool {
+        if !mg.in_bounds(pos.x, pos.y) {
+            return false;
+        }
+        self.maps.entry(map.to_string()).or_default().insert(mg.xy_index(pos.x, pos.y))
+    }
+
+    pub fn is_explored(&self, map: &str, mg: &MapGrid, x: i32, y: i32) -> bool {
+        self.maps.get(map).map(|cells| cells.contains(&mg.xy_index(x, y))).unwrap_or(false)
+    }
+}
+
+// Marks the party's cell whenever they move or arrive on a new map
+pub fn mark_explored(
+    party: Query<Ref<Position>, With<PlayerParty>>,
+    current: Option<Res<CurrentMap>>,
+    mg: Res<MapGrid>,
+    mut explored: ResMut<Explored>,
+) {
+    let (Ok(pos), Some(current)) = (party.get_single(), current) else { return };
+    // Arriving through a transition bypasses change detection, so a new map counts too
+    if !pos.is_changed() && !current.is_changed() {
+        return;
+    }
+    explored.mark(&current.path, &mg, &pos);
+}
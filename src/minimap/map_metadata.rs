@@ -0,0 +1,151 @@
+// Optional metadata block that rides along with a SavedMap
+// Geometry lives in MapGrid/WallGrid - this is everything else about the map (What it's called, what plays, etc...)
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::minimap::*;
+
+// Every field has a default so older map files (Or partially filled blocks) still load cleanly
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct MapMetadata {
+    pub name: String,
+    pub author: String,
+    pub description: String,
+    pub floor: i32,
+    pub music: Option<String>,          // Identifier for the background track, not a filepath
+    pub ambience: Option<String>,
+    pub encounter_table: Option<String>, // Id into the encounter tables, if the map has random encounters
+    pub tags: Vec<String>,
+    pub created: u64,   // Unix timestamps (seconds) - 0 means it was never set
+    pub modified: u64,
+}
+
+impl MapMetadata {
+    // Stamps the modified time (And created time, if this is the first save)
+    pub fn touch(&mut self) {
+        let now = unix_timestamp();
+        if self.created == 0 {
+            self.created = now;
+        }
+        self.modified = now;
+    }
+
+    // Name to show the player - falls back to the floor number if nobody named the map
+    pub fn display_name(&self) -> String {
+        if self.name.is_empty() {
+            format!("Floor {}", self.floor)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// Fields exposed in the MapBuilder's metadata panel - each one knows how to read/write itself as text
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataField {
+    Name,
+    Author,
+    Description,
+    Floor,
+    Music,
+    Ambience,
+    EncounterTable,
+    Tags,
+}
+
+impl MetadataField {
+    pub const ALL: [MetadataField; 8] = [
+        MetadataField::Name,
+        MetadataField::Author,
+        MetadataField::Description,
+        MetadataField::Floor,
+        MetadataField::Music,
+        MetadataField::Ambience,
+        MetadataField::EncounterTable,
+        MetadataField::Tags,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MetadataField::Name => "Name",
+            MetadataField::Author => "Author",
+            MetadataField::Description => "Description",
+            MetadataField::Floor => "Floor",
+            MetadataField::Music => "Music",
+            MetadataField::Ambience => "Ambience",
+            MetadataField::EncounterTable => "Encounters",
+            MetadataField::Tags => "Tags",
+        }
+    }
+
+    pub fn get(&self, meta: &MapMetadata) -> String {
+        match self {
+            MetadataField::Name => meta.name.clone(),
+            MetadataField::Author => meta.author.clone(),
+            MetadataField::Description => meta.description.clone(),
+            MetadataField::Floor => meta.floor.to_string(),
+            MetadataField::Music => meta.music.clone().unwrap_or_default(),
+            MetadataField::Ambience => meta.ambience.clone().unwrap_or_default(),
+            MetadataField::EncounterTable => meta.encounter_table.clone().unwrap_or_default(),
+            MetadataField::Tags => meta.tags.join(","),
+        }
+    }
+
+    // Writes the text back into the metadata - blank optional fields become None
+    // Floor silently keeps its old value if the text isn't a number yet (EG just typed a '-')
+    pub fn set(&self, meta: &mut MapMetadata, value: &str) {
+        let optional = |v: &str| if v.trim().is_empty() { None } else { Some(v.to_string()) };
+        match self {
+            MetadataField::Name => meta.name = value.to_string(),
+            MetadataField::Author => meta.author = value.to_string(),
+            MetadataField::Description => meta.description = value.to_string(),
+            MetadataField::Floor => {
+                if let Ok(floor) = value.trim().parse::<i32>() {
+                    meta.floor = floor;
+                } else if value.trim().is_empty() {
+                    meta.floor = 0;
+                }
+            }
+            MetadataField::Music => meta.music = optional(value),
+            MetadataField::Ambience => meta.ambience = optional(value),
+            MetadataField::EncounterTable => meta.encounter_table = optional(value),
+            MetadataField::Tags => {
+                meta.tags = value
+                    .split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect();
+            }
+        }
+    }
+}
+
+// Game-side hook - runs whenever a new map's metadata gets inserted so the rest of the game can react
+// For now this just retitles the window and logs it, music/ambience will hook in here once audio exists
+pub fn map_metadata_on_load(
+    meta: Res<MapMetadata>,
+    mut window: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if !meta.is_changed() {
+        return;
+    }
+    println!("Entered map: {} (Floor {})", meta.display_name(), meta.floor);
+    if let Ok(mut window) = window.get_single_mut() {
+        window.title = format!("Rusty Odyssey - {}", meta.display_name());
+    }
+}
@@ -0,0 +1,154 @@
+// Handles moving the party between maps when they step onto a TransitionTile
+// Flow: party lands on a TransitionTile -> PendingTransition is stored -> TurnState::EnterDungeon
+//       -> old map snapshotted into WorldState -> old map unloaded, new map loaded, party placed
+//       -> minimap redrawn -> TurnState::AwaitingInput
+// Maps the party has already been to come back out of WorldState, so broken walls, beaten foes and sprung traps stay that way
+
+use std::collections::BTreeMap;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{party::{Party, PlayerParty}, Health, Position};
+use crate::enemies::*;
+use crate::items::{Inventory, ItemLibrary};
+use crate::minimap::*;
+use crate::resources::*;
+
+// Where the party is headed - only exists between stepping on the tile and the load finishing
+#[derive(Resource, Clone, Debug)]
+pub struct PendingTransition {
+    pub dest: String,
+    pub loc: Position,
+}
+
+// Last known state of every map the party has left, keyed by map path - goes into save files
+#[derive(Resource, Serialize, Deserialize, Clone, Default)]
+pub struct WorldState {
+    pub maps: BTreeMap<String, SavedMap>,
+}
+
+impl WorldState {
+    // Visited maps come from here, anything else straight from its file
+    pub fn load_map(&self, path: &str) -> Result<SavedMap, String> {
+        match self.maps.get(path) {
+            Some(map) => Ok(map.clone()),
+            None => SavedMap::try_from_file(path),
+        }
+    }
+}
+
+// Everything needed to write the loaded map back out as a SavedMap, runtime state included
+#[derive(SystemParam)]
+pub struct MapSnapshot<'w, 's> {
+    mg: Res<'w, MapGrid>,
+    wg: Res<'w, WallGrid>,
+    meta: Option<Res<'w, MapMetadata>>,
+    tile_events: Query<'w, 's, (&'static Position, Option<&'static TransitionTile>, Option<&'static TrapTile>, Option<&'static EventTile>), With<TileEvent>>,
+    foes: Query<'w, 's, (&'static Party, &'static Position, &'static Foe)>,
+    breakables: Query<'w, 's, (&'static Position, &'static Health, &'static Breakable)>,
+}
+
+impl<'w, 's> MapSnapshot<'w, 's> {
+    pub fn capture(&self) -> SavedMap {
+        let map = SavedMap::new(self.wg.clone(), self.mg.clone())
+            .with_events(collect_tile_events(&self.tile_events))
+            .with_foes(collect_foes(&self.foes))
+            .with_breakables(collect_breakables(&self.breakables));
+        match &self.meta {
+            Some(meta) => map.with_meta(meta.as_ref().clone()),
+            None => map,
+        }
+    }
+}
+
+// OnEnter(TurnState::EnterDungeon), before enter_dungeon - remembers the map being left
+pub fn snapshot_current_map(
+    pending: Option<Res<PendingTransition>>,
+    current: Option<Res<CurrentMap>>,
+    snapshot: MapSnapshot,
+    mut world: ResMut<WorldState>,
+) {
+    let (Some(_), Some(current)) = (pending, current) else { return };
+    world.maps.insert(current.path.clone(), snapshot.capture());
+}
+
+// Checks if the party just moved onto a transition tile, and kicks off the map change if so
+pub fn check_transition_tiles(
+    mut commands: Commands,
+    party: Query<(&Position, Option<&Inventory>), (With<PlayerParty>, Changed<Position>)>,
+    tiles: Query<(&Position, &TransitionTile), With<TileEvent>>,
+    items: Res<ItemLibrary>,
+    mut next_turn: ResMut<NextState<TurnState>>,
+) {
+    let Ok((party_pos, inventory)) = party.get_single() else { return };
+
+    if let Some((_, transition)) = tiles.iter().find(|(pos, _)| *pos == party_pos) {
+        // Locked - the party stays put on the tile until they come back with the key
+        if let Some(key) = &transition.requires {
+            if !inventory.map(|i| i.has(key)).unwrap_or(false) {
+                println!("It's locked. You'll need the {} to get through.", items.name(key));
+                return;
+            }
+        }
+        println!("Transitioning to {} at ({}, {})", transition.dest, transition.loc.x, transition.loc.y);
+        commands.insert_resource(PendingTransition {
+            dest: transition.dest.clone(),
+            loc: transition.loc.clone(),
+        });
+        next_turn.set(TurnState::EnterDungeon);
+    }
+}
+
+// OnEnter(TurnState::EnterDungeon) - performs the actual swap
+// Any failure (Missing file, bad data, destination off the map) leaves the current map in place
+pub fn enter_dungeon(
+    mut commands: Commands,
+    pending: Option<Res<PendingTransition>>,
+    tile_events: Query<Entity, With<TileEvent>>,
+    foes: Query<Entity, With<Foe>>,
+    breakables: Query<Entity, With<Breakable>>,
+    mut party: Query<(&mut Position, &mut Transform), With<PlayerParty>>,
+    world: Res<WorldState>,
+    mut next_turn: ResMut<NextState<TurnState>>,
+    mut next_render: ResMut<NextState<MapBuildState>>,
+) {
+    // Nothing pending means this is the initial entry - the starting map was already loaded during setup
+    let Some(pending) = pending else {
+        next_turn.set(TurnState::AwaitingInput);
+        return;
+    };
+    commands.remove_resource::<PendingTransition>();
+    next_turn.set(TurnState::AwaitingInput);
+
+    let map_data = match world.load_map(&pending.dest) {
+        Ok(map_data) => map_data,
+        Err(e) => {
+            println!("Map transition failed - {}", e);
+            return;
+        }
+    };
+    let mg = map_data.get_mg();
+    if !mg.in_bounds(pending.loc.x, pending.loc.y) {
+        println!("Map transition failed - ({}, {}) is outside of {}", pending.loc.x, pending.loc.y, pending.dest);
+        return;
+    }
+
+    // Unload the old map's tile events, foes and breakables before the new ones are spawned in
+    unload_tile_events(&mut commands, &tile_events);
+    unload_foes(&mut commands, &foes);
+    unload_breakables(&mut commands, &breakables);
+    insert_map(&mut commands, &map_data, &pending.dest);
+
+    if let Ok((mut pos, mut transform)) = party.get_single_mut() {
+        // Bypassed so arriving on a tile doesn't count as stepping onto it (No bouncing straight back up the stairs)
+        *pos.bypass_change_detection() = pending.loc.clone();
+        let world = grid_to_world(&mg, &pending.loc);
+        transform.translation.x = world.x;
+        transform.translation.y = world.y;
+    }
+
+    // Redraw the minimap with the new map's grid and walls
+    next_render.set(MapBuildState::RenderMap);
+}
@@ -271,3 +271,48 @@ pub fn mouse_wall_gui(
 
     
 }
+
+// Icon currently picked in the builder - None means clicks in a cell do nothing
+#[derive(Resource, Default)]
+pub struct IconBrush(pub Option<MapIcon>);
+
+// Stamps icons onto cells - 1-7 picks an icon from the palette (0 puts the brush away),
+// then left-clicking the middle of a cell marks it (Again to clear). Clicks near edges/corners are left to the wall tool
+pub fn mouse_icon_gui(
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mouse: Res<Input<MouseButton>>,
+    input: Res<Input<KeyCode>>,
+    map_cam: Query<(&Camera, &GlobalTransform)>,
+    mut brush: ResMut<IconBrush>,
+    mut mg: ResMut<MapGrid>,
+) {
+    let keys = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6, KeyCode::Key7];
+    for (key, icon) in keys.into_iter().zip(MAP_ICONS) {
+        if input.just_pressed(key) {
+            brush.0 = Some(icon);
+            println!("Icon brush: {:?}", icon);
+        }
+    }
+    if input.just_pressed(KeyCode::Key0) {
+        brush.0 = None;
+        println!("Icon brush put away");
+    }
+
+    let Some(icon) = brush.0 else { return };
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let (camera, camera_transform) = map_cam.single();
+    let Some(world_position) = q_window.single().cursor_position()
+        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
+        .map(|ray| ray.origin.truncate()) else { return };
+
+    let grid = world_to_grid(&mg, world_position);
+    let (x, y) = (grid.x.round() as i32, grid.y.round() as i32);
+    if (grid.x - x as f32).abs() > 0.3 || (grid.y - y as f32).abs() > 0.3 {
+        return;
+    }
+    if mg.in_bounds(x, y) {
+        mg.toggle_icon(x, y, icon);
+    }
+}
@@ -5,7 +5,7 @@ use std::io::BufWriter;
 
 use bevy::prelude::*;
 
-use crate::{components::*, minimap::*, resources::*, };
+use crate::{components::*, components::party::Party, enemies::*, minimap::*, };
 
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash, States)]
@@ -15,6 +15,7 @@ pub enum MBMenuState {
     Save,
     Load,
     New,
+    Metadata,
 }
 
 // Defining a few menu constants, mainly for hover/click colors
@@ -25,15 +26,20 @@ const PRESSED_BUTTON: Color = Color::GREEN;
 
 
 // Plugin that manages the menu itself (Mainly state changes)
-pub fn menu_plugin(app: &mut App){
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
     // TODO - move some of map_builder into this 
     // Mainly for add_systems OnEnter() and OnExit() for specific states
-    app
-        .add_systems(OnEnter(MBMenuState::Save), save_gui)
-        // Add a system that checks to see if it's completed saving yet or not?
-        .add_systems(OnExit(MBMenuState::Save), save_complete)
-    
-    ;
+    fn build(&self, app: &mut App) {
+        app
+            // save_complete is what takes us back out of Save, so it has to run on the way in (Straight after the dialog closes)
+            .add_systems(OnEnter(MBMenuState::Save), (save_gui, save_complete).chain())
+            // Metadata panel - opened/closed from the Map Info button
+            .add_systems(OnEnter(MBMenuState::Metadata), metadata_gui)
+            .add_systems(Update, (metadata_select, metadata_typing, metadata_text_update).run_if(in_state(MBMenuState::Metadata)))
+            .add_systems(OnExit(MBMenuState::Metadata), (despawn_system::<MetadataPanel>, metadata_complete));
+    }
 }
 
 // Function for the menu pane on the left side of the window
@@ -140,6 +146,22 @@ pub fn menu_setup(mut commands: Commands, asset_server: Res<AssetServer>){
                             ));
                         })
                     ;
+
+                    // Map Info Button - toggles the metadata panel
+                    parent
+                        .spawn((ButtonBundle {
+                            style: btn_style.clone(),
+                            background_color: Color::GRAY.into(),
+                            ..default()
+                        },
+                        MenuButtonActions::MapInfo,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("Map Info", btn_text_style.clone(),
+                            
+                            ));
+                        })
+                    ;
                 })
             ;
         })
@@ -165,6 +187,7 @@ pub fn menu_button_system(
 
 pub fn menu_action(
     interaction_query: Query<(&Interaction, &MenuButtonActions), (Changed<Interaction>, With<Button>)>,
+    cur_state: Res<State<MBMenuState>>,
     mut menu_state: ResMut<NextState<MBMenuState>>,
     
 ){
@@ -183,6 +206,14 @@ pub fn menu_action(
                     // For now, we'll just reset the map without any warnings and add that safeguard later
                     menu_state.set(MBMenuState::New);
                 }
+                MenuButtonActions::MapInfo => {
+                    // Acts as a toggle - pressing it again closes the panel
+                    if *cur_state.get() == MBMenuState::Metadata {
+                        menu_state.set(MBMenuState::Awaiting);
+                    } else {
+                        menu_state.set(MBMenuState::Metadata);
+                    }
+                }
                 _ => {
                     // Unimplemented case
                     println!("{:?} has not been implemented yet!", menu_button_action);
@@ -199,6 +230,10 @@ pub fn save_gui(
     mut commands: Commands,
     mg: Res<MapGrid>,
     mw: Res<WallGrid>,
+    mut meta: ResMut<MapMetadata>,
+    tile_events: Query<(&Position, Option<&TransitionTile>, Option<&TrapTile>, Option<&EventTile>), With<TileEvent>>,
+    foes: Query<(&Party, &Position, &Foe)>,
+    breakables: Query<(&Position, &Health, &Breakable)>,
 ) {
     // Experimenting with RFD - do I need Async, or can I just wait since I don't need to simulate anything?
     // For the map-builder, doing non-async is probably fine for the initial mockup
@@ -216,8 +251,15 @@ pub fn save_gui(
         .set_directory(std::env::current_dir().unwrap())
         .save_file();
 
-    // Once user has picked a file out, write the MapGrid and WallGrid to the file
-    let map_data: SavedMap = SavedMap::new(mw.as_ref().clone(), mg.as_ref().clone());
+    // Once user has picked a file out, write the MapGrid and WallGrid (and metadata) to the file
+    if file.is_some() {
+        meta.touch();
+    }
+    let map_data: SavedMap = SavedMap::new(mw.as_ref().clone(), mg.as_ref().clone())
+        .with_meta(meta.clone())
+        .with_events(collect_tile_events(&tile_events))
+        .with_foes(collect_foes(&foes))
+        .with_breakables(collect_breakables(&breakables));
     let map_string = serde_json::to_string(&map_data);
 
     // Note - The file isn't actually created in the FileDialog - we do get an absolute path 
@@ -286,3 +328,150 @@ fn new_gui(){}
 // Clean up after new is completed
 fn new_complete(){}
 
+
+
+// Marker for everything spawned by the metadata panel, so it can be despawned in one go
+#[derive(Component)]
+pub struct MetadataPanel;
+
+// Text node showing the value of a given field
+#[derive(Component)]
+pub struct MetadataFieldText(pub MetadataField);
+
+// Which field is being typed into, and the raw text so far
+// Kept separately from MapMetadata so things like a trailing comma in Tags don't get eaten mid-typing
+#[derive(Resource, Default)]
+pub struct MetadataEditBuffer {
+    pub field: Option<MetadataField>,
+    pub text: String,
+}
+
+// Spawns the metadata panel on the right side - one clickable row per field
+pub fn metadata_gui(mut commands: Commands, meta: Res<MapMetadata>){
+    let row_style = Style{
+        width: Val::Px(280.),
+        height: Val::Px(30.),
+        margin: UiRect::all(Val::Px(4.0)),
+        padding: UiRect::horizontal(Val::Px(6.0)),
+        align_items: AlignItems::Center,
+        ..default()
+    };
+    let text_style = TextStyle{
+        font_size: 16.0,
+        color: Color::BLACK,
+        ..default()
+    };
+
+    commands.insert_resource(MetadataEditBuffer::default());
+    commands
+        .spawn((NodeBundle{
+            style: Style{
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                position_type: PositionType::Absolute,
+                right: Val::Px(0.),
+                bottom: Val::Px(0.),
+                ..default()
+            },
+            background_color: Color::CRIMSON.into(),
+            ..default()
+        },
+        MetadataPanel,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section("Map Info (Esc to close)", text_style.clone()));
+            for field in MetadataField::ALL {
+                parent
+                    .spawn((ButtonBundle{
+                        style: row_style.clone(),
+                        background_color: NORMAL_BUTTON.into(),
+                        ..default()
+                    },
+                    field,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section(format!("{}: {}", field.label(), field.get(&meta)), text_style.clone()),
+                            MetadataFieldText(field),
+                        ));
+                    });
+            }
+        });
+}
+
+// Clicking a field selects it for typing (Uses SelectedOption so menu_button_system keeps it highlighted)
+pub fn metadata_select(
+    mut commands: Commands,
+    interaction_query: Query<(&Interaction, &MetadataField, Entity), (Changed<Interaction>, With<Button>)>,
+    selected: Query<Entity, (With<SelectedOption>, With<MetadataField>)>,
+    meta: Res<MapMetadata>,
+    mut buffer: ResMut<MetadataEditBuffer>,
+){
+    for (interaction, field, entity) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            for prev in &selected {
+                commands.entity(prev).remove::<SelectedOption>();
+            }
+            commands.entity(entity).insert(SelectedOption);
+            buffer.field = Some(*field);
+            buffer.text = field.get(&meta);
+        }
+    }
+}
+
+// Routes typed characters into the selected field - Escape closes the panel
+pub fn metadata_typing(
+    mut chars: EventReader<ReceivedCharacter>,
+    input: Res<Input<KeyCode>>,
+    mut buffer: ResMut<MetadataEditBuffer>,
+    mut meta: ResMut<MapMetadata>,
+    mut menu_state: ResMut<NextState<MBMenuState>>,
+){
+    if input.just_pressed(KeyCode::Escape) {
+        menu_state.set(MBMenuState::Awaiting);
+        return;
+    }
+    let Some(field) = buffer.field else {
+        chars.clear();
+        return;
+    };
+
+    let mut changed = false;
+    if input.just_pressed(KeyCode::Back) {
+        buffer.text.pop();
+        changed = true;
+    }
+    for ev in chars.read() {
+        // Backspace/Enter/etc... also arrive as characters - skip the control ones
+        if !ev.char.is_control() {
+            buffer.text.push(ev.char);
+            changed = true;
+        }
+    }
+    if changed {
+        field.set(&mut meta, &buffer.text);
+    }
+}
+
+// Keeps the displayed values in sync - the selected field shows the raw buffer instead
+pub fn metadata_text_update(
+    meta: Res<MapMetadata>,
+    buffer: Res<MetadataEditBuffer>,
+    mut texts: Query<(&mut Text, &MetadataFieldText)>,
+){
+    if !meta.is_changed() && !buffer.is_changed() {
+        return;
+    }
+    for (mut text, field_text) in &mut texts {
+        let value = if buffer.field == Some(field_text.0) {
+            format!("{}_", buffer.text)
+        } else {
+            field_text.0.get(&meta)
+        };
+        text.sections[0].value = format!("{}: {}", field_text.0.label(), value);
+    }
+}
+
+pub fn metadata_complete(mut commands: Commands){
+    commands.remove_resource::<MetadataEditBuffer>();
+}
@@ -4,6 +4,7 @@ use bevy::render::view::visibility::RenderLayers;
 use bevy::window::*;
 use bevy::prelude::*;
 use serde::*;
+use std::collections::BTreeMap;
 use std::fs::*;
 use std::io::Read;
 // use crate::components::*;
@@ -13,10 +14,26 @@ pub use draw_map::*;
 pub mod mb_menu;
 pub use mb_menu::*;
 pub mod tile_component;
+pub use tile_component::*;
 pub mod mb_map_builder;
 pub use mb_map_builder::*;
+pub mod map_metadata;
+pub use map_metadata::*;
+pub mod tile_properties;
+pub use tile_properties::*;
+pub mod map_transition;
+pub use map_transition::*;
+pub mod traps;
+pub use traps::*;
+pub mod breakables;
+pub use breakables::*;
+pub mod explored;
+pub use explored::*;
+pub mod cartography;
+pub use cartography::*;
 
 use crate::components::Position;
+use crate::enemies::*;
 
 
 #[derive(Component)]
@@ -139,6 +156,12 @@ pub struct MapGrid {
     pub dim_x: i32,
     pub dim_y: i32,
     pub zoom: f32,
+    // Per-cell custom properties, keyed by xy_index - see tile_properties.rs
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub props: BTreeMap<i32, TileProps>,
+    // Icons marked on cells (Doors, stairs, chests, etc...), keyed by xy_index - see draw_map.rs
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub icons: BTreeMap<i32, MapIcon>,
 }
 
 impl MapGrid {
@@ -148,6 +171,8 @@ impl MapGrid {
             dim_y: height,
             tiles: vec![Tile {walls:[false,false,false,false],}; (width*height) as usize],
             zoom: ZOOM_LEVEL,
+            props: BTreeMap::new(),
+            icons: BTreeMap::new(),
         }
     }
 
@@ -157,6 +182,11 @@ impl MapGrid {
         (y * self.dim_x ) + x
     }
 
+    // Is the (x,y) cell actually on the map
+    pub fn in_bounds(&self, x:i32, y:i32) -> bool {
+        x >= 0 && y >= 0 && x < self.dim_x && y < self.dim_y
+    }
+
     // Given a line of 2 points, figure out which 1-2 grids are involved
     // TODO - Fix the values fed into xy_index and the mappings - some of it's correct? 
     pub fn grid_index(&self, x1:i32, y1:i32, x2:i32, y2:i32) -> Result<[i32;2], String> {
@@ -347,16 +377,48 @@ impl MapGrid {
     }
 }
 
-#[derive(Serialize,Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct SavedMap {
     pub w: WallGrid,
     pub m: MapGrid,
+    // Optional so maps saved before metadata existed still load
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<MapMetadata>,
+    // Transitions, traps and events placed on the map - spawned as TileEvent entities on load
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<PlacedTileEvent>,
+    // Roaming enemies placed on the map - see enemies.rs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub foes: Vec<PlacedFoe>,
+    // Barrels, cracked walls, etc... - see breakables.rs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub breakables: Vec<PlacedBreakable>,
 }
 
 // All functions in here are intended for the save/load logic
 impl SavedMap{
     pub fn new(w: WallGrid, m: MapGrid) -> Self{
-        SavedMap{w: w, m: m}
+        SavedMap{w: w, m: m, meta: None, events: Vec::new(), foes: Vec::new(), breakables: Vec::new()}
+    }
+
+    pub fn with_events(mut self, events: Vec<PlacedTileEvent>) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn with_foes(mut self, foes: Vec<PlacedFoe>) -> Self {
+        self.foes = foes;
+        self
+    }
+
+    pub fn with_breakables(mut self, breakables: Vec<PlacedBreakable>) -> Self {
+        self.breakables = breakables;
+        self
+    }
+
+    pub fn with_meta(mut self, meta: MapMetadata) -> Self {
+        self.meta = Some(meta);
+        self
     }
     
     pub fn get_wg(&self) -> WallGrid {
@@ -367,18 +429,30 @@ impl SavedMap{
         self.m.clone()
     }
 
+    // Maps without a metadata block just get a blank one
+    pub fn get_meta(&self) -> MapMetadata {
+        self.meta.clone().unwrap_or_default()
+    }
+
     pub fn create_from_file(path: String) -> Self{
         // Attempt to open file from given path
         // If it panics, it means our default map is inacessible (and likely all other maps)
-        let mut file = File::open(path).unwrap();
-        // let mut data = String::new();
-        // file.read_to_string(&mut data).unwrap();
+        SavedMap::try_from_file(&path).unwrap()
+    }
 
-        let saved_map = serde_json::from_reader(file);
-        saved_map.unwrap()
+    // Non-panicking version for loads that can fail at runtime (Map transitions, etc...)
+    pub fn try_from_file(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Unable to open map {}: {}", path, e))?;
+        serde_json::from_reader(file).map_err(|e| format!("Unable to parse map {}: {}", path, e))
     }
 }
 
+// Tracks which map file is currently loaded - transitions and saves need to know where we are
+#[derive(Resource, Clone, Debug, Default)]
+pub struct CurrentMap {
+    pub path: String,
+}
+
 // Initialization function for the initial map grid and wall grid (Game startup)
 //| Right now, they're treated as a resource rather than Entities, since only 1 map is loaded at any given time
 //| This may change down the line if certain maps need to be cached, although those might just become their own resources
@@ -387,6 +461,7 @@ pub fn build_init(mut commands: Commands){
     let wg = WallGrid::new(8,8);
     commands.insert_resource(mg);
     commands.insert_resource(wg);
+    commands.insert_resource(MapMetadata::default());
 }
 
 // Initialization function - Loads from file rather than a blank map
@@ -394,16 +469,36 @@ pub fn build_from_file(mut commands: Commands, path: &str){
     // File will have a SavedMap json - load into that, then load in the resources from it
     // Just calls insert_resource since that overwrites the resource cleanly - May tweak later if we need to cache previous resource.
     let map_data = SavedMap::create_from_file(path.to_string());
-    
+    insert_map(&mut commands, &map_data, path);
+}
+
+// Shared by the initial load and map transitions - swaps in the map resources and spawns its tile events
+pub fn insert_map(commands: &mut Commands, map_data: &SavedMap, path: &str){
     // Use our clone functions so we can let insert_resource own the structs
     commands.insert_resource(map_data.get_mg());
     commands.insert_resource(map_data.get_wg());
-
+    commands.insert_resource(map_data.get_meta());
+    commands.insert_resource(CurrentMap { path: path.to_string() });
+    spawn_tile_events(commands, &map_data.events);
+    spawn_foes(commands, &map_data.m, &map_data.foes);
+    spawn_breakables(commands, &map_data.m, &map_data.breakables);
 }
 
 // Update function to replace the resource - needs a ResMut of the resources
 // Just overwrite the resource, unless we need to cache the previous one first
 
+// Translates a grid Position into world coordinates, using the same bottom-left shift as draw_grid
+pub fn grid_to_world(mg: &MapGrid, pos: &Position) -> Vec2 {
+    let bl_x_shift = mg.dim_x as f32 * mg.zoom / 2. - mg.zoom/2.;
+    let bl_y_shift = mg.dim_y as f32 * mg.zoom / 2. - mg.zoom/2.;
+    Vec2::new(pos.x as f32 * mg.zoom - bl_x_shift, pos.y as f32 * mg.zoom - bl_y_shift)
+}
+
+// Inverse of grid_to_world - left fractional so callers can tell where in the cell a point is (Cell centres are whole numbers)
+pub fn world_to_grid(mg: &MapGrid, world: Vec2) -> Vec2 {
+    (world - grid_to_world(mg, &Position { x: 0, y: 0, z: 0 })) / mg.zoom
+}
+
 // Helper function to convert from floating point coordinate to pixel it's part of
 pub fn coord_to_grid(x: f32, y: f32) -> (i32, i32) {
     // Just use floor function to truncate floating point and return the X/Y values
@@ -1,8 +1,9 @@
 // Supporting file that handles mainly components for a given tile location
 
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::{components::*, minimap::*, resources::*, };
+use crate::{components::*, resources::*, scripting::*, };
 
 
 
@@ -10,38 +11,198 @@ use crate::{components::*, minimap::*, resources::*, };
     In order to associate 'events' with a given location on a minimap, doing the following
     1) New entity created, with Coordinate, TileData, and other supporting components
     2) When player moves, check all TileEvents to see if we're on top of one (All entities with TileEvent should have Position)
-
+    3) Map files store these as PlacedTileEvents - spawned on map load, despawned on unload
 */
 #[derive(Component)]
 pub struct TileEvent;
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
 pub struct TransitionTile {
     pub dest: String,  // Filepath or index to the destination map
     pub loc: Position, // Location on the destination map to be spawned at
+    #[serde(default)]
+    pub requires: Option<String>, // Key item needed to get through - locked otherwise
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
 pub struct TrapTile {
-    pub trap_type: String, // TODO -replace with enum
+    pub trap_type: TrapKind,
     pub loc: Option<Position>, // Optional secondary location - for arrow traps as an example (Where is it shooting from?)
+    // Runtime state - saved alongside the map so a sprung/disarmed trap stays that way
+    #[serde(default)]
+    pub detected: bool,
+    #[serde(default)]
+    pub disarmed: bool,
+    #[serde(default)]
+    pub triggered: u32,  // Number of times it has gone off
+}
+
+// Catalog of trap types - the numbers behind each one come from fetch_trap()
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrapKind {
+    Pit,
+    Spikes,
+    Arrow,      // Fires from `loc` - loses damage over distance
+    Teleporter, // Sends the party to `loc`
+    Alarm,      // Calls in an encounter
+}
+
+// Specific details for a trap type
+#[derive(Clone, Copy, Debug)]
+pub struct TrapDetails {
+    pub damage: i32,
+    pub detect_chance: i32, // Percent chance to spot the trap before stepping on it
+    pub disarm_chance: i32, // Percent chance to disarm once spotted
+    pub one_shot: bool,     // One-shot traps are spent after going off once
+}
+
+// What happens when a trap goes off - applied by the trap system
+#[derive(Clone, Debug, Default)]
+pub struct TrapOutcome {
+    pub damage: i32,
+    pub teleport: Option<Position>,
+    pub alarm: bool,
 }
 
 impl TrapTile {
+    pub fn new(trap_type: TrapKind, loc: Option<Position>) -> Self {
+        TrapTile { trap_type, loc, detected: false, disarmed: false, triggered: 0 }
+    }
+
     // Obtains specific details to the trap (Damage, etc...)
-    pub fn fetch_trap(&self){}
+    pub fn fetch_trap(&self) -> TrapDetails {
+        match self.trap_type {
+            TrapKind::Pit => TrapDetails { damage: 8, detect_chance: 40, disarm_chance: 0, one_shot: false },
+            TrapKind::Spikes => TrapDetails { damage: 5, detect_chance: 50, disarm_chance: 60, one_shot: false },
+            TrapKind::Arrow => TrapDetails { damage: 10, detect_chance: 35, disarm_chance: 50, one_shot: false },
+            TrapKind::Teleporter => TrapDetails { damage: 0, detect_chance: 25, disarm_chance: 30, one_shot: false },
+            TrapKind::Alarm => TrapDetails { damage: 0, detect_chance: 30, disarm_chance: 70, one_shot: true },
+        }
+    }
+
+    // Spent or disarmed traps don't do anything anymore
+    pub fn is_active(&self) -> bool {
+        !self.disarmed && !(self.fetch_trap().one_shot && self.triggered > 0)
+    }
+
+    // Party stepped on the trap - rolls detection/disarm first, then fires if those fail
+    // Returns None if the trap was avoided (Or already inactive)
+    pub fn spring(&mut self, at: &Position, rng: &mut GameRng) -> Option<TrapOutcome> {
+        if !self.is_active() {
+            return None;
+        }
+        let details = self.fetch_trap();
+
+        if !self.detected && rng.roll(details.detect_chance) {
+            self.detected = true;
+            println!("Spotted a {:?} trap!", self.trap_type);
+        }
+        // Pits can't be disarmed - spotting one just gives a chance to step around it
+        if self.detected {
+            if details.disarm_chance == 0 {
+                if rng.roll(50) {
+                    println!("Carefully stepped around the {:?} trap", self.trap_type);
+                    return None;
+                }
+            } else if rng.roll(details.disarm_chance) {
+                self.disarmed = true;
+                println!("Disarmed the {:?} trap", self.trap_type);
+                return None;
+            }
+        }
+
+        self.triggered += 1;
+        let mut outcome = TrapOutcome::default();
+        match self.trap_type {
+            TrapKind::Pit | TrapKind::Spikes => {
+                outcome.damage = details.damage;
+            }
+            TrapKind::Arrow => {
+                // Damage falls off the further away it was fired from (Minimum of 1)
+                let dist = self.loc.as_ref().map(|l| (l.x - at.x).abs() + (l.y - at.y).abs()).unwrap_or(0);
+                outcome.damage = (details.damage - dist).max(1);
+            }
+            TrapKind::Teleporter => {
+                outcome.teleport = self.loc.clone();
+            }
+            TrapKind::Alarm => {
+                outcome.alarm = true;
+            }
+        }
+        Some(outcome)
+    }
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
 pub struct EventTile {
-    // TODO - add parameters to this (Method to fetch specific event from file?)
+    #[serde(default)]
+    pub event_id: String, // Id of the scripted event in the EventLibrary (assets/data/events.json)
 }
 
 impl EventTile{
-    // Obtains event data and stores it so we can access it upon reaching the tile
-    pub fn fetch_event(&self){
-        
+    // Looks up the event data so we can run it upon reaching the tile
+    pub fn fetch_event<'a>(&self, lib: &'a EventLibrary) -> Option<&'a ScriptedEvent> {
+        lib.get(&self.event_id)
     }
 }
 
+// Serialized form of the tile events - one entry per event in the map file
+// Tagged with "type" so the file reads as {"pos": {...}, "type": "Trap", "trap_type": "...", ...}
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type")]
+pub enum TileEventKind {
+    Transition(TransitionTile),
+    Trap(TrapTile),
+    Event(EventTile),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PlacedTileEvent {
+    pub pos: Position,
+    #[serde(flatten)]
+    pub kind: TileEventKind,
+}
 
+// Spawns every tile event from a map as its own entity (TileEvent + Position + the event's component)
+pub fn spawn_tile_events(commands: &mut Commands, events: &[PlacedTileEvent]) {
+    for event in events {
+        // Older maps marked event tiles with a bare {"type": "Event"} - there's nothing to run on those yet
+        if matches!(&event.kind, TileEventKind::Event(e) if e.event_id.is_empty()) {
+            continue;
+        }
+        let mut entity = commands.spawn((TileEvent, event.pos.clone()));
+        match &event.kind {
+            TileEventKind::Transition(t) => { entity.insert(t.clone()); }
+            TileEventKind::Trap(t) => { entity.insert(t.clone()); }
+            TileEventKind::Event(e) => { entity.insert(e.clone()); }
+        }
+    }
+}
+
+// Inverse of spawn_tile_events - gathers the live tile events back up so they can be written to a SavedMap
+pub fn collect_tile_events(
+    query: &Query<(&Position, Option<&TransitionTile>, Option<&TrapTile>, Option<&EventTile>), With<TileEvent>>,
+) -> Vec<PlacedTileEvent> {
+    let mut events = Vec::new();
+    for (pos, transition, trap, event) in query.iter() {
+        let kind = if let Some(t) = transition {
+            TileEventKind::Transition(t.clone())
+        } else if let Some(t) = trap {
+            TileEventKind::Trap(t.clone())
+        } else if let Some(e) = event {
+            TileEventKind::Event(e.clone())
+        } else {
+            // Bare TileEvent with nothing attached - nothing worth saving
+            continue;
+        };
+        events.push(PlacedTileEvent { pos: pos.clone(), kind });
+    }
+    events
+}
+
+// Unloads the current map's tile events - run before a new map is brought in
+pub fn unload_tile_events(commands: &mut Commands, tile_events: &Query<Entity, With<TileEvent>>) {
+    for entity in tile_events.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
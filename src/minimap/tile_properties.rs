@@ -0,0 +1,120 @@
+// Free-form key/value properties attached to individual cells of a MapGrid
+// Meant for one-off mechanics (Dark zones, damage floors, conveyor tiles, etc...) so they don't each need a new field on Tile
+// Stored sparsely on the MapGrid - most cells will never have any properties
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::components::Position;
+use crate::minimap::*;
+
+// Untagged so the map file stays readable - {"dark": true, "damage": 5, "note": "Watch out"}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum TileProp {
+    Bool(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl TileProp {
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            TileProp::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            TileProp::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_i32(&self) -> Option<i32> {
+        self.as_f64().map(|n| n as i32)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            TileProp::Text(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+}
+
+impl From<bool> for TileProp {
+    fn from(value: bool) -> Self { TileProp::Bool(value) }
+}
+impl From<i32> for TileProp {
+    fn from(value: i32) -> Self { TileProp::Number(value as f64) }
+}
+impl From<f64> for TileProp {
+    fn from(value: f64) -> Self { TileProp::Number(value) }
+}
+impl From<&str> for TileProp {
+    fn from(value: &str) -> Self { TileProp::Text(value.to_string()) }
+}
+impl From<String> for TileProp {
+    fn from(value: String) -> Self { TileProp::Text(value) }
+}
+
+// Properties of a single cell - BTreeMap so saved maps diff cleanly
+pub type TileProps = BTreeMap<String, TileProp>;
+
+// Property accessors live with the rest of MapGrid's helpers, keyed by xy_index()
+impl MapGrid {
+    pub fn set_tile_prop(&mut self, x: i32, y: i32, key: &str, value: impl Into<TileProp>) -> Result<(), String> {
+        if !self.in_bounds(x, y) {
+            return Err(String::from("Coordinate out of bounds"))
+        }
+        let index = self.xy_index(x, y);
+        self.props.entry(index).or_default().insert(key.to_string(), value.into());
+        Ok(())
+    }
+
+    // Returns the old value if there was one - empty cells are dropped so the file doesn't fill with {}
+    pub fn remove_tile_prop(&mut self, x: i32, y: i32, key: &str) -> Option<TileProp> {
+        if !self.in_bounds(x, y) {
+            return None
+        }
+        let index = self.xy_index(x, y);
+        let cell = self.props.get_mut(&index)?;
+        let old = cell.remove(key);
+        if cell.is_empty() {
+            self.props.remove(&index);
+        }
+        old
+    }
+
+    pub fn get_tile_prop(&self, x: i32, y: i32, key: &str) -> Option<&TileProp> {
+        if !self.in_bounds(x, y) {
+            return None
+        }
+        self.props.get(&self.xy_index(x, y))?.get(key)
+    }
+
+    // Shorthand for game systems that already have the party/entity Position
+    pub fn prop_at(&self, pos: &Position, key: &str) -> Option<&TileProp> {
+        self.get_tile_prop(pos.x, pos.y, key)
+    }
+
+    pub fn tile_props(&self, x: i32, y: i32) -> Option<&TileProps> {
+        if !self.in_bounds(x, y) {
+            return None
+        }
+        self.props.get(&self.xy_index(x, y))
+    }
+
+    // Every cell that has the given key, as (x, y, value)
+    pub fn cells_with_prop(&self, key: &str) -> Vec<(i32, i32, &TileProp)> {
+        self.props
+            .iter()
+            .filter_map(|(index, cell)| {
+                cell.get(key).map(|value| (index % self.dim_x, index / self.dim_x, value))
+            })
+            .collect()
+    }
+}
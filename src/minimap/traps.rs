@@ -0,0 +1,57 @@
+// Trap system - fires TrapTiles when the party steps onto them
+// The trap catalog itself (Damage, chances, effects) lives with TrapTile in tile_component.rs
+
+use bevy::prelude::*;
+
+use crate::components::{party::{Party, PlayerParty}, HealthEvents, Position};
+use crate::encounters::*;
+use crate::minimap::*;
+use crate::resources::*;
+
+pub fn check_trap_tiles(
+    mut commands: Commands,
+    mut party: Query<(Entity, &mut Position, &mut Transform, &mut Party), (With<PlayerParty>, Changed<Position>)>,
+    mut traps: Query<(&Position, &mut TrapTile), (With<TileEvent>, Without<PlayerParty>)>,
+    mut rng: ResMut<GameRng>,
+    mg: Res<MapGrid>,
+    meta: Option<Res<MapMetadata>>,
+    tables: Res<EncounterTables>,
+    mut next_turn: ResMut<NextState<TurnState>>,
+    mut next_combat: ResMut<NextState<CombatState>>,
+    mut health_events: HealthEvents,
+) {
+    let Ok((entity, mut party_pos, mut transform, mut party)) = party.get_single_mut() else { return };
+
+    for (trap_pos, mut trap) in traps.iter_mut() {
+        if *trap_pos != *party_pos {
+            continue;
+        }
+        let Some(outcome) = trap.spring(trap_pos, &mut rng) else { continue };
+        println!("Triggered a {:?} trap!", trap.trap_type);
+
+        // Traps catch everyone still standing
+        if outcome.damage > 0 {
+            for (slot, member) in party.members.iter_mut().enumerate().filter(|(_, m)| m.character.is_alive()) {
+                let health = &mut member.character.health;
+                let taken = health_events.damage(health, outcome.damage, entity, Some(slot));
+                println!("{} takes {} damage ({} HP left)", member.character.name, taken, health.hp());
+            }
+        }
+        if let Some(dest) = outcome.teleport {
+            if mg.in_bounds(dest.x, dest.y) {
+                // Bypassed so landing on another trap doesn't chain-fire it
+                *party_pos.bypass_change_detection() = dest.clone();
+                let world = grid_to_world(&mg, &dest);
+                transform.translation.x = world.x;
+                transform.translation.y = world.y;
+            }
+        }
+        if outcome.alarm {
+            // Calls in whatever lives on this part of the map
+            let group = tables.table_at(&mg, meta.as_deref(), &party_pos).and_then(|t| t.pick_group(&mut rng));
+            start_battle(&mut commands, group, &mut next_turn, &mut next_combat);
+        }
+        // Only one trap per tile gets to fire
+        break;
+    }
+}
@@ -61,4 +61,98 @@ pub enum CombatState {
     Computing,
     Executing,
     ExitingCombat,  // Includes Fleeing and winning
-}
\ No newline at end of file
+}
+
+// Game-wide random number generator - kept as our own small xorshift so the exact state can be saved and restored later
+// Anything random that affects game state (Traps, encounters, combat rolls) should pull from this instead of a thread RNG
+#[derive(Resource, Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GameRng {
+    pub seed: u64,
+    state: u64,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        // Xorshift gets stuck on a zero state, so nudge it off
+        let state = if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed };
+        GameRng { seed, state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        // xorshift64*
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // Random number in [min, max) - returns min if the range is empty
+    pub fn range(&mut self, min: i32, max: i32) -> i32 {
+        if max <= min {
+            return min;
+        }
+        min + (self.next_u64() % (max - min) as u64) as i32
+    }
+
+    // Percentage roll - true with a `chance` in 100 probability
+    pub fn roll(&mut self, chance: i32) -> bool {
+        self.range(0, 100) < chance
+    }
+}
+
+impl Default for GameRng {
+    // Seeds from the clock - saves/tests that need determinism should use GameRng::new(seed)
+    fn default() -> Self {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(1);
+        GameRng::new(seed)
+    }
+}
+
+// Story flags set/checked by events and dialogue, plus which one-shot events have already run
+#[derive(Resource, Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct StoryFlags {
+    pub flags: std::collections::BTreeMap<String, bool>,
+    pub completed_events: std::collections::BTreeSet<String>,
+}
+
+impl StoryFlags {
+    // Unset flags read as false
+    pub fn get(&self, flag: &str) -> bool {
+        self.flags.get(flag).copied().unwrap_or(false)
+    }
+
+    pub fn set(&mut self, flag: &str, value: bool) {
+        self.flags.insert(flag.to_string(), value);
+    }
+}
+
+// Battle waiting to start - set by whatever kicked off combat (Foe contact, traps, events) and read once combat begins
+#[derive(Resource, Debug, Clone, Default)]
+pub struct PendingBattle {
+    pub encounter: Option<String>, // Id of the enemy group to fight
+    pub foe: Option<Entity>,       // On-map enemy that started it, if any
+}
+
+// Picked when starting a game and saved with it - for now it only decides how much the game maps for you
+#[derive(Resource, Debug, Clone, Copy, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum Difficulty {
+    #[default]
+    Casual,     // The minimap shows the real map, nothing to draw
+    Normal,     // The player draws their own map, auto-map assist can be turned on
+    Expert,     // The player draws their own map, no assist
+}
+
+impl Difficulty {
+    pub fn cartography(self) -> bool {
+        self != Difficulty::Casual
+    }
+
+    pub fn allows_auto_map(self) -> bool {
+        self == Difficulty::Normal
+    }
+}
@@ -0,0 +1,434 @@
+// Game saves - everything needed to put the game back exactly where it was
+// The party, their bag and position, story flags, explored cells, the RNG state, and the state of every map
+// that's been visited (The current one included, snapshotted at save time), plus the encounter odds and any NPCs
+// Only taken while the turn loop is waiting on input, so there's never a battle or event half way through
+//
+// Save files are two lines - a small SlotInfo header, then the SaveGame itself
+// The header carries what the load screen lists (Location, playtime, etc...) plus a checksum of the body,
+// so slots can be listed and checked for corruption without deserializing every save
+
+use std::fs::{self, create_dir_all};
+use std::io::ErrorKind;
+use std::path::Path;
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{party::{Party, PlayerParty}, Facing, Position};
+use crate::dialogue::{spawn_npc, Npc};
+use crate::encounters::EncounterDanger;
+use crate::enemies::{unload_foes, Foe};
+use crate::items::Inventory;
+use crate::minimap::*;
+use crate::resources::*;
+use crate::turn::{TurnCounter, TurnPhase};
+
+pub const SAVE_DIR: &str = "saves";
+pub const SAVE_SLOTS: u32 = 3;      // Numbered slots the player picks
+pub const AUTOSAVE_SLOTS: u32 = 3;  // Autosaves rotate through these, overwriting the oldest
+// Bumped whenever the save layout changes in a way older files can't be read with
+pub const SAVE_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveSlot {
+    Quick,
+    Manual(u32),
+    Auto(u32),
+}
+
+impl SaveSlot {
+    pub fn path(&self) -> String {
+        match self {
+            SaveSlot::Quick => format!("{}/quicksave.json", SAVE_DIR),
+            SaveSlot::Manual(n) => format!("{}/slot{}.json", SAVE_DIR, n),
+            SaveSlot::Auto(n) => format!("{}/autosave{}.json", SAVE_DIR, n),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            SaveSlot::Quick => "Quicksave".to_string(),
+            SaveSlot::Manual(n) => format!("Slot {}", n),
+            SaveSlot::Auto(n) => format!("Autosave {}", n),
+        }
+    }
+
+    // Every slot in the order a load screen shows them
+    pub fn all() -> Vec<SaveSlot> {
+        let mut slots = vec![SaveSlot::Quick];
+        slots.extend((1..=SAVE_SLOTS).map(SaveSlot::Manual));
+        slots.extend((1..=AUTOSAVE_SLOTS).map(SaveSlot::Auto));
+        slots
+    }
+}
+
+// Seconds spent in game - only counts while the app is running, and carries across saves
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct PlayTime {
+    pub secs: f64,
+}
+
+impl PlayTime {
+    // H:MM:SS
+    pub fn display(&self) -> String {
+        let total = self.secs as u64;
+        format!("{}:{:02}:{:02}", total / 3600, total / 60 % 60, total % 60)
+    }
+}
+
+pub fn tick_playtime(time: Res<Time>, mut playtime: ResMut<PlayTime>) {
+    playtime.secs += time.delta_seconds_f64();
+}
+
+// Header line of a save file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SlotInfo {
+    pub version: u32,
+    pub timestamp: u64,      // Unix seconds when it was written
+    pub playtime: PlayTime,
+    pub location: String,    // Display name of the map the party was on
+    pub party_level: u32,    // Highest level in the party
+    pub checksum: u64,       // Of the body line, exactly as written
+}
+
+// What the load screen gets for each slot
+#[derive(Clone, Debug)]
+pub enum SlotStatus {
+    Empty,
+    Ready(SlotInfo),
+    Corrupted(String),
+}
+
+// FNV-1a - plenty to catch a truncated or hand-edited file, this isn't meant to stop tampering
+pub fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| (hash ^ *b as u64).wrapping_mul(0x100000001b3))
+}
+
+// Splits a save into its header and body, checking the body hasn't been damaged since it was written
+fn read_slot_file(path: &str) -> Result<Option<(SlotInfo, String)>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Unable to open save {}: {}", path, e)),
+    };
+    let (header, body) = contents.split_once('\n').ok_or_else(|| format!("Save {} is missing its header", path))?;
+    let info: SlotInfo = serde_json::from_str(header).map_err(|e| format!("Save {} has a bad header: {}", path, e))?;
+    if info.version != SAVE_VERSION {
+        return Err(format!("Save {} is version {}, expected {}", path, info.version, SAVE_VERSION));
+    }
+    if checksum(body.as_bytes()) != info.checksum {
+        return Err(format!("Save {} is corrupted (Checksum mismatch)", path));
+    }
+    Ok(Some((info, body.to_string())))
+}
+
+pub fn slot_status(slot: SaveSlot) -> SlotStatus {
+    match read_slot_file(&slot.path()) {
+        Ok(None) => SlotStatus::Empty,
+        Ok(Some((info, _))) => SlotStatus::Ready(info),
+        Err(e) => SlotStatus::Corrupted(e),
+    }
+}
+
+pub fn list_slots() -> Vec<(SaveSlot, SlotStatus)> {
+    SaveSlot::all().into_iter().map(|slot| (slot, slot_status(slot))).collect()
+}
+
+// Autosaves go into the first empty slot, otherwise over the oldest - broken ones count as oldest
+pub fn next_autosave_slot() -> SaveSlot {
+    (1..=AUTOSAVE_SLOTS)
+        .map(SaveSlot::Auto)
+        .min_by_key(|slot| match slot_status(*slot) {
+            SlotStatus::Empty => (0, 0),
+            SlotStatus::Corrupted(_) => (1, 0),
+            SlotStatus::Ready(info) => (2, info.timestamp),
+        })
+        .unwrap_or(SaveSlot::Auto(1))
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SaveGame {
+    pub version: u32,
+    pub map: String,  // Path of the map the party is on - its state is in world.maps
+    pub position: Position,
+    #[serde(default)]
+    pub facing: Facing,
+    pub party: Party,
+    pub inventory: Inventory,
+    pub flags: StoryFlags,
+    pub explored: Explored,
+    pub world: WorldState,
+    pub rng: GameRng,
+    pub turns: TurnCounter,
+    #[serde(default)]
+    pub playtime: PlayTime,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    #[serde(default)]
+    pub cartography: Cartography,
+    #[serde(default)]
+    pub danger: EncounterDanger,
+    // NPCs are placed at startup rather than coming from the map, so they're kept here
+    // None for saves made before they were - loading those leaves whatever NPCs are already out
+    #[serde(default)]
+    pub npcs: Option<Vec<SavedNpc>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SavedNpc {
+    pub npc: Npc,
+    pub position: Position,
+}
+
+impl SaveGame {
+    pub fn slot_info(&self, checksum: u64) -> SlotInfo {
+        SlotInfo {
+            version: self.version,
+            timestamp: unix_timestamp(),
+            playtime: self.playtime,
+            location: self.world.maps.get(&self.map).map(|m| m.get_meta().display_name()).unwrap_or_default(),
+            party_level: self.party.members.iter().map(|m| m.character.level).max().unwrap_or(0),
+            checksum,
+        }
+    }
+
+    pub fn write_to_slot(&self, slot: SaveSlot) -> Result<(), String> {
+        let path = slot.path();
+        if let Some(dir) = Path::new(&path).parent() {
+            create_dir_all(dir).map_err(|e| format!("Unable to create {}: {}", dir.display(), e))?;
+        }
+        let body = serde_json::to_string(self).map_err(|e| format!("Unable to write save {}: {}", path, e))?;
+        let header = serde_json::to_string(&self.slot_info(checksum(body.as_bytes())))
+            .map_err(|e| format!("Unable to write save {}: {}", path, e))?;
+        fs::write(&path, format!("{}\n{}", header, body)).map_err(|e| format!("Unable to write save {}: {}", path, e))
+    }
+
+    pub fn read_from_slot(slot: SaveSlot) -> Result<Self, String> {
+        let path = slot.path();
+        let (_, body) = read_slot_file(&path)?.ok_or_else(|| format!("{} is empty", slot.label()))?;
+        let save: SaveGame = serde_json::from_str(&body).map_err(|e| format!("Unable to parse save {}: {}", path, e))?;
+        if save.version != SAVE_VERSION {
+            return Err(format!("Save {} is version {}, expected {}", path, save.version, SAVE_VERSION));
+        }
+        if !save.world.maps.contains_key(&save.map) {
+            return Err(format!("Save {} is missing its current map {}", path, save.map));
+        }
+        Ok(save)
+    }
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct SaveGameRequest {
+    pub slot: SaveSlot,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct LoadGameRequest {
+    pub slot: SaveSlot,
+}
+
+// Set when the party changes maps - the autosave waits until the new map is in and the turn loop is back on input
+#[derive(Resource)]
+pub struct AutosavePending;
+
+const SLOT_KEYS: [KeyCode; SAVE_SLOTS as usize] = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3];
+
+// F5 quicksaves, F9 quickloads, F6 lists the slots
+// Ctrl + 1-3 saves to a numbered slot, Shift + 1-3 loads from one
+pub fn save_keys(
+    input: Res<Input<KeyCode>>,
+    mut saves: EventWriter<SaveGameRequest>,
+    mut loads: EventWriter<LoadGameRequest>,
+) {
+    if input.just_pressed(KeyCode::F5) {
+        saves.send(SaveGameRequest { slot: SaveSlot::Quick });
+    }
+    if input.just_pressed(KeyCode::F9) {
+        loads.send(LoadGameRequest { slot: SaveSlot::Quick });
+    }
+    if input.just_pressed(KeyCode::F6) {
+        print_slots();
+    }
+    let ctrl = input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    for (i, key) in SLOT_KEYS.iter().enumerate() {
+        if !input.just_pressed(*key) {
+            continue;
+        }
+        let slot = SaveSlot::Manual(i as u32 + 1);
+        if ctrl {
+            saves.send(SaveGameRequest { slot });
+        } else if shift {
+            loads.send(LoadGameRequest { slot });
+        }
+    }
+}
+
+// Stand-in for a load screen until there's a menu for it
+pub fn print_slots() {
+    for (slot, status) in list_slots() {
+        match status {
+            SlotStatus::Empty => println!("{:<12} - empty", slot.label()),
+            SlotStatus::Ready(info) => println!("{:<12} - {} | Lv {} | {} | saved {}",
+                slot.label(), info.location, info.party_level, info.playtime.display(), info.timestamp),
+            SlotStatus::Corrupted(e) => println!("{:<12} - unreadable ({})", slot.label(), e),
+        }
+    }
+}
+
+// OnEnter(TurnState::EnterDungeon) - only map changes get an autosave, not the initial entry
+pub fn queue_autosave(mut commands: Commands, pending: Option<Res<PendingTransition>>) {
+    if pending.is_some() {
+        commands.insert_resource(AutosavePending);
+    }
+}
+
+pub fn autosave(mut commands: Commands, mut saves: EventWriter<SaveGameRequest>) {
+    commands.remove_resource::<AutosavePending>();
+    saves.send(SaveGameRequest { slot: next_autosave_slot() });
+}
+
+// The game-wide state that goes into a save alongside the party and the current map
+#[derive(SystemParam)]
+pub struct SaveState<'w, 's> {
+    world: Res<'w, WorldState>,
+    flags: Res<'w, StoryFlags>,
+    explored: Res<'w, Explored>,
+    rng: Res<'w, GameRng>,
+    turns: Res<'w, TurnCounter>,
+    playtime: Res<'w, PlayTime>,
+    difficulty: Res<'w, Difficulty>,
+    cartography: Res<'w, Cartography>,
+    danger: Res<'w, EncounterDanger>,
+    npcs: Query<'w, 's, (&'static Npc, &'static Position)>,
+}
+
+impl<'w, 's> SaveState<'w, 's> {
+    // Builds the save from the live game - the current map is written into the world state alongside the others
+    pub fn capture(
+        &self,
+        (party, inventory, position, facing): (&Party, &Inventory, &Position, &Facing),
+        current: &CurrentMap,
+        snapshot: &MapSnapshot,
+    ) -> SaveGame {
+        let mut world = self.world.clone();
+        world.maps.insert(current.path.clone(), snapshot.capture());
+        SaveGame {
+            version: SAVE_VERSION,
+            map: current.path.clone(),
+            position: position.clone(),
+            facing: *facing,
+            party: party.clone(),
+            inventory: inventory.clone(),
+            flags: self.flags.clone(),
+            explored: self.explored.clone(),
+            world,
+            rng: self.rng.clone(),
+            turns: *self.turns,
+            playtime: *self.playtime,
+            difficulty: *self.difficulty,
+            cartography: self.cartography.clone(),
+            danger: self.danger.clone(),
+            npcs: Some(self.npcs.iter()
+                .map(|(npc, position)| SavedNpc { npc: npc.clone(), position: position.clone() })
+                .collect()),
+        }
+    }
+}
+
+pub fn save_game(
+    mut requests: EventReader<SaveGameRequest>,
+    party: Query<(&Party, &Inventory, &Position, &Facing), With<PlayerParty>>,
+    current: Option<Res<CurrentMap>>,
+    snapshot: MapSnapshot,
+    state: SaveState,
+) {
+    for request in requests.read() {
+        let (Some(current), Ok(party)) = (&current, party.get_single()) else {
+            println!("Nothing to save yet");
+            continue;
+        };
+        match state.capture(party, current, &snapshot).write_to_slot(request.slot) {
+            Ok(()) => println!("Game saved to {}", request.slot.label()),
+            Err(e) => println!("Save failed - {}", e),
+        }
+    }
+}
+
+// Swaps the whole game state out for the save's - a bad file leaves the current game untouched
+pub fn load_game(
+    mut commands: Commands,
+    mut requests: EventReader<LoadGameRequest>,
+    tile_events: Query<Entity, With<TileEvent>>,
+    foes: Query<Entity, With<Foe>>,
+    breakables: Query<Entity, With<Breakable>>,
+    npcs: Query<Entity, With<Npc>>,
+    mut party: Query<(&mut Party, &mut Inventory, &mut Position, &mut Facing, &mut Transform), With<PlayerParty>>,
+    mut next_turn: ResMut<NextState<TurnState>>,
+    mut next_render: ResMut<NextState<MapBuildState>>,
+) {
+    let Some(request) = requests.read().last() else { return };
+    let save = match SaveGame::read_from_slot(request.slot) {
+        Ok(save) => save,
+        Err(e) => {
+            println!("Load failed - {}", e);
+            return;
+        }
+    };
+    let Ok((mut party, mut inventory, mut pos, mut facing, mut transform)) = party.get_single_mut() else { return };
+    let map_data = &save.world.maps[&save.map];
+
+    unload_tile_events(&mut commands, &tile_events);
+    unload_foes(&mut commands, &foes);
+    unload_breakables(&mut commands, &breakables);
+    insert_map(&mut commands, map_data, &save.map);
+    if let Some(saved) = &save.npcs {
+        for entity in npcs.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        for npc in saved {
+            spawn_npc(&mut commands, &map_data.m, &npc.npc.name, &npc.npc.dialogue, npc.position.clone());
+        }
+    }
+
+    *party = save.party.clone();
+    *inventory = save.inventory.clone();
+    // Bypassed so loading onto a tile doesn't set it off
+    *pos.bypass_change_detection() = save.position.clone();
+    *facing = save.facing;
+    let world = grid_to_world(&map_data.m, &save.position);
+    transform.translation.x = world.x;
+    transform.translation.y = world.y;
+
+    commands.insert_resource(save.flags.clone());
+    commands.insert_resource(save.explored.clone());
+    commands.insert_resource(save.rng.clone());
+    commands.insert_resource(save.turns);
+    commands.insert_resource(save.world.clone());
+    commands.insert_resource(save.playtime);
+    commands.insert_resource(save.difficulty);
+    commands.insert_resource(save.cartography.clone());
+    commands.insert_resource(save.danger.clone());
+    commands.remove_resource::<PendingTransition>();
+    commands.remove_resource::<PendingBattle>();
+    commands.remove_resource::<AutosavePending>();
+
+    println!("Loaded {}", request.slot.label());
+    next_turn.set(TurnState::AwaitingInput);
+    next_render.set(MapBuildState::RenderMap);
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<SaveGameRequest>()
+            .add_event::<LoadGameRequest>()
+            .init_resource::<PlayTime>()
+            .add_systems(Update, tick_playtime)
+            .add_systems(Update, (save_keys, autosave.run_if(resource_exists::<AutosavePending>())).in_set(TurnPhase::Input))
+            .add_systems(Update, (save_game, load_game).chain().after(TurnPhase::Input));
+    }
+}
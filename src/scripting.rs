@@ -0,0 +1,410 @@
+// Data-driven event scripts - what happens when the party steps onto an EventTile
+// Events are loaded from assets/data/events.json as a list of steps, run one at a time by an EventRunner
+// Anything the script can't do by itself (Items, battles) is sent out as a ScriptActionEvent for the owning system
+
+use std::collections::HashMap;
+use std::fs::File;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::components::{party::PlayerParty, Position};
+use crate::items::Inventory;
+use crate::minimap::*;
+use crate::resources::*;
+use crate::textbox::*;
+use crate::turn::TurnPhase;
+
+pub const EVENT_FILE: &str = "assets/data/events.json";
+
+// Guards against scripts that Goto themselves forever without ever showing anything
+const MAX_STEPS_PER_ADVANCE: usize = 1000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EventTrigger {
+    #[default]
+    OneShot,    // Only ever runs once per game
+    Repeatable,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EventChoice {
+    pub text: String,
+    #[serde(default)]
+    pub goto: Option<usize>, // Step to jump to - None just carries on to the next step
+}
+
+// Steps are referenced by index for Goto/CheckFlag/Choice jumps
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "op")]
+pub enum EventStep {
+    Message {
+        #[serde(default)]
+        speaker: Option<String>,
+        text: String,
+    },
+    Choice {
+        prompt: String,
+        options: Vec<EventChoice>,
+    },
+    CheckFlag {
+        flag: String,
+        #[serde(default)]
+        if_set: Option<usize>,
+        #[serde(default)]
+        if_unset: Option<usize>,
+    },
+    SetFlag {
+        flag: String,
+        #[serde(default = "default_true")]
+        value: bool,
+    },
+    GiveItem {
+        item: String,
+        #[serde(default = "default_one")]
+        qty: u32,
+    },
+    CheckItem {
+        item: String,
+        #[serde(default = "default_one")]
+        qty: u32,
+        #[serde(default)]
+        if_have: Option<usize>,
+        #[serde(default)]
+        if_missing: Option<usize>,
+    },
+    StartBattle {
+        #[serde(default)]
+        encounter: Option<String>,
+    },
+    MoveParty {
+        #[serde(default)]
+        map: Option<String>, // None stays on the current map
+        loc: Position,
+    },
+    Goto {
+        step: usize,
+    },
+    End,
+}
+
+fn default_true() -> bool { true }
+fn default_one() -> u32 { 1 }
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScriptedEvent {
+    pub id: String,
+    #[serde(default)]
+    pub trigger: EventTrigger,
+    pub steps: Vec<EventStep>,
+}
+
+// All events known to the game, by id
+#[derive(Resource, Default)]
+pub struct EventLibrary {
+    pub events: HashMap<String, ScriptedEvent>,
+}
+
+impl EventLibrary {
+    pub fn load_from_file(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Unable to open events {}: {}", path, e))?;
+        let events: Vec<ScriptedEvent> = serde_json::from_reader(file)
+            .map_err(|e| format!("Unable to parse events {}: {}", path, e))?;
+        Ok(EventLibrary {
+            events: events.into_iter().map(|e| (e.id.clone(), e)).collect(),
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&ScriptedEvent> {
+        self.events.get(id)
+    }
+}
+
+// Things a script asks the rest of the game to do
+#[derive(Clone, Debug)]
+pub enum ScriptAction {
+    GiveItem { item: String, qty: u32 },
+    StartBattle { encounter: Option<String> },
+    MoveParty { map: Option<String>, loc: Position },
+}
+
+#[derive(Event, Clone, Debug)]
+pub struct ScriptActionEvent(pub ScriptAction);
+
+// Result of running a script forward until it needs something
+#[derive(Clone, Debug)]
+pub enum ScriptYield {
+    Message { speaker: Option<String>, text: String },
+    Choice { prompt: String, options: Vec<String> },
+    Action(ScriptAction),
+    Finished,
+}
+
+#[derive(Clone, Debug)]
+enum Awaiting {
+    Confirm,
+    Choice(Vec<Option<usize>>),
+}
+
+// The event currently running - only exists while an event is in progress
+#[derive(Resource, Clone, Debug)]
+pub struct EventRunner {
+    pub event_id: String,
+    pub pc: usize,
+    awaiting: Option<Awaiting>,
+    pub exit_to: TurnState, // Where the turn loop goes once the event is over
+}
+
+impl EventRunner {
+    pub fn new(event_id: &str) -> Self {
+        EventRunner { event_id: event_id.to_string(), pc: 0, awaiting: None, exit_to: TurnState::AwaitingInput }
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        self.awaiting.is_some()
+    }
+
+    // Runs steps until one needs the player or the outside world
+    pub fn advance(&mut self, event: &ScriptedEvent, flags: &mut StoryFlags, inventory: &Inventory) -> ScriptYield {
+        for _ in 0..MAX_STEPS_PER_ADVANCE {
+            let Some(step) = event.steps.get(self.pc) else {
+                return ScriptYield::Finished;
+            };
+            match step {
+                EventStep::Message { speaker, text } => {
+                    self.pc += 1;
+                    self.awaiting = Some(Awaiting::Confirm);
+                    return ScriptYield::Message { speaker: speaker.clone(), text: text.clone() };
+                }
+                EventStep::Choice { prompt, options } => {
+                    self.pc += 1;
+                    self.awaiting = Some(Awaiting::Choice(options.iter().map(|o| o.goto).collect()));
+                    return ScriptYield::Choice {
+                        prompt: prompt.clone(),
+                        options: options.iter().map(|o| o.text.clone()).collect(),
+                    };
+                }
+                EventStep::CheckFlag { flag, if_set, if_unset } => {
+                    let jump = if flags.get(flag) { *if_set } else { *if_unset };
+                    self.pc = jump.unwrap_or(self.pc + 1);
+                }
+                EventStep::SetFlag { flag, value } => {
+                    flags.set(flag, *value);
+                    self.pc += 1;
+                }
+                EventStep::GiveItem { item, qty } => {
+                    self.pc += 1;
+                    return ScriptYield::Action(ScriptAction::GiveItem { item: item.clone(), qty: *qty });
+                }
+                EventStep::CheckItem { item, qty, if_have, if_missing } => {
+                    let jump = if inventory.count(item) >= *qty { *if_have } else { *if_missing };
+                    self.pc = jump.unwrap_or(self.pc + 1);
+                }
+                EventStep::StartBattle { encounter } => {
+                    // The battle takes over from here, so the script ends
+                    self.pc = event.steps.len();
+                    return ScriptYield::Action(ScriptAction::StartBattle { encounter: encounter.clone() });
+                }
+                EventStep::MoveParty { map, loc } => {
+                    self.pc += 1;
+                    return ScriptYield::Action(ScriptAction::MoveParty { map: map.clone(), loc: loc.clone() });
+                }
+                EventStep::Goto { step } => {
+                    self.pc = *step;
+                }
+                EventStep::End => {
+                    self.pc = event.steps.len();
+                    return ScriptYield::Finished;
+                }
+            }
+        }
+        println!("Event {} ran too many steps without yielding - stopping it", self.event_id);
+        ScriptYield::Finished
+    }
+
+    // Dismisses a message - returns false if we weren't waiting on one
+    pub fn confirm(&mut self) -> bool {
+        if matches!(self.awaiting, Some(Awaiting::Confirm)) {
+            self.awaiting = None;
+            return true;
+        }
+        false
+    }
+
+    // Picks a choice by index - out of range picks are ignored
+    pub fn choose(&mut self, index: usize) -> bool {
+        if let Some(Awaiting::Choice(gotos)) = &self.awaiting {
+            if let Some(goto) = gotos.get(index) {
+                if let Some(step) = goto {
+                    self.pc = *step;
+                }
+                self.awaiting = None;
+                return true;
+            }
+        }
+        false
+    }
+}
+
+pub fn load_event_library(mut commands: Commands) {
+    match EventLibrary::load_from_file(EVENT_FILE) {
+        Ok(lib) => commands.insert_resource(lib),
+        Err(e) => {
+            println!("{} - continuing without events", e);
+            commands.insert_resource(EventLibrary::default());
+        }
+    }
+}
+
+// Starts an event if the party just stepped onto an EventTile that can still run
+pub fn check_event_tiles(
+    mut commands: Commands,
+    party: Query<&Position, (With<PlayerParty>, Changed<Position>)>,
+    tiles: Query<(&Position, &EventTile), With<TileEvent>>,
+    lib: Res<EventLibrary>,
+    flags: Res<StoryFlags>,
+    mut next_turn: ResMut<NextState<TurnState>>,
+    mut next_gameplay: ResMut<NextState<GameplayState>>,
+) {
+    let Ok(party_pos) = party.get_single() else { return };
+
+    for (pos, tile) in tiles.iter() {
+        if pos != party_pos {
+            continue;
+        }
+        let Some(event) = tile.fetch_event(&lib) else {
+            println!("EventTile references unknown event {}", tile.event_id);
+            continue;
+        };
+        if event.trigger == EventTrigger::OneShot && flags.completed_events.contains(&event.id) {
+            continue;
+        }
+        commands.insert_resource(EventRunner::new(&event.id));
+        next_turn.set(TurnState::EnterDialogue);
+        next_gameplay.set(GameplayState::Dialogue);
+        break;
+    }
+}
+
+// Drives the running event - waits on the text box, then runs the script forward to the next thing to show
+pub fn run_event(
+    mut commands: Commands,
+    mut runner: ResMut<EventRunner>,
+    lib: Res<EventLibrary>,
+    mut flags: ResMut<StoryFlags>,
+    mut inputs: EventReader<TextBoxInput>,
+    boxes: Query<Entity, With<TextBox>>,
+    mut party: Query<(&mut Position, &mut Transform), With<PlayerParty>>,
+    inventory: Query<&Inventory, With<PlayerParty>>,
+    mg: Res<MapGrid>,
+    current_map: Option<Res<CurrentMap>>,
+    mut actions: EventWriter<ScriptActionEvent>,
+    mut next_turn: ResMut<NextState<TurnState>>,
+    mut next_gameplay: ResMut<NextState<GameplayState>>,
+) {
+    // Waiting on the player - only move on once they respond
+    if runner.is_waiting() {
+        let mut responded = false;
+        for input in inputs.read() {
+            responded |= match input {
+                TextBoxInput::Confirm => runner.confirm(),
+                TextBoxInput::Choose(i) => runner.choose(*i),
+            };
+        }
+        if !responded {
+            return;
+        }
+        despawn_textbox(&mut commands, &boxes);
+    } else {
+        // Anything pressed before the box came up shouldn't count as a response
+        inputs.clear();
+    }
+
+    let Some(event) = lib.get(&runner.event_id) else {
+        finish_event(&mut commands, &runner, None, &mut flags, &mut next_turn, &mut next_gameplay);
+        return;
+    };
+
+    let no_items = Inventory::default();
+    let inventory = inventory.get_single().unwrap_or(&no_items);
+    loop {
+        match runner.advance(event, &mut flags, inventory) {
+            ScriptYield::Message { speaker, text } => {
+                spawn_textbox(&mut commands, speaker.as_deref(), &text, &[]);
+                return;
+            }
+            ScriptYield::Choice { prompt, options } => {
+                spawn_textbox(&mut commands, None, &prompt, &options);
+                return;
+            }
+            ScriptYield::Action(action) => {
+                match &action {
+                    ScriptAction::StartBattle { .. } => {
+                        runner.exit_to = TurnState::EnterCombat;
+                    }
+                    ScriptAction::MoveParty { map, loc } => {
+                        let same_map = match (map, &current_map) {
+                            (None, _) => true,
+                            (Some(map), Some(cur)) => *map == cur.path,
+                            (Some(_), None) => false,
+                        };
+                        if same_map {
+                            if let Ok((mut pos, mut transform)) = party.get_single_mut() {
+                                if mg.in_bounds(loc.x, loc.y) {
+                                    *pos.bypass_change_detection() = loc.clone();
+                                    let world = grid_to_world(&mg, loc);
+                                    transform.translation.x = world.x;
+                                    transform.translation.y = world.y;
+                                }
+                            }
+                        } else if let Some(map) = map {
+                            // Different map - hand it to the map transition logic once the event wraps up
+                            commands.insert_resource(PendingTransition { dest: map.clone(), loc: loc.clone() });
+                            runner.exit_to = TurnState::EnterDungeon;
+                        }
+                    }
+                    ScriptAction::GiveItem { .. } => {}
+                }
+                actions.send(ScriptActionEvent(action));
+            }
+            ScriptYield::Finished => {
+                finish_event(&mut commands, &runner, Some(event), &mut flags, &mut next_turn, &mut next_gameplay);
+                return;
+            }
+        }
+    }
+}
+
+fn finish_event(
+    commands: &mut Commands,
+    runner: &EventRunner,
+    event: Option<&ScriptedEvent>,
+    flags: &mut StoryFlags,
+    next_turn: &mut NextState<TurnState>,
+    next_gameplay: &mut NextState<GameplayState>,
+) {
+    if let Some(event) = event {
+        if event.trigger == EventTrigger::OneShot {
+            flags.completed_events.insert(event.id.clone());
+        }
+    }
+    commands.remove_resource::<EventRunner>();
+    next_gameplay.set(GameplayState::Exploration);
+    next_turn.set(runner.exit_to);
+}
+
+pub struct ScriptingPlugin;
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<StoryFlags>()
+            .add_event::<ScriptActionEvent>()
+            .add_systems(Startup, load_event_library)
+            .add_systems(Update, check_event_tiles.in_set(TurnPhase::Player))
+            .add_systems(Update, run_event
+                .after(textbox_input)
+                .run_if(in_state(GameplayState::Dialogue))
+                .run_if(resource_exists::<EventRunner>()));
+    }
+}
@@ -0,0 +1,366 @@
+// Skills - defined in assets/data/skills.json, learned per class by spending skill points
+// Every skill is a list of effects run through one interpreter (apply_effects), so a heal cast in combat
+// and the same heal cast from the field menu behave exactly the same
+// Anything that can be hit by a skill implements SkillTarget (Combatants in battle, Characters in the field)
+
+use std::collections::HashMap;
+use std::fs::File;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::combat::engine::Combatant;
+use crate::components::{character::*, status::*};
+use crate::resources::GameRng;
+
+pub const SKILL_FILE: &str = "assets/data/skills.json";
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetSide {
+    User,   // Only ever hits whoever used it
+    Ally,
+    Enemy,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetScope {
+    Single,
+    Row,    // Everyone in the same row as the chosen target
+    All,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Targeting {
+    pub side: TargetSide,
+    pub scope: TargetScope,
+}
+
+impl Targeting {
+    // Single and row skills make the player pick someone (The row is whichever one they're in)
+    pub fn needs_target(&self) -> bool {
+        self.side != TargetSide::User && self.scope != TargetScope::All
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Usable {
+    #[default]
+    Combat,
+    Field,
+    Both,
+}
+
+impl Usable {
+    pub fn in_combat(&self) -> bool {
+        matches!(self, Usable::Combat | Usable::Both)
+    }
+
+    pub fn in_field(&self) -> bool {
+        matches!(self, Usable::Field | Usable::Both)
+    }
+}
+
+// Which of the user's numbers a damage effect scales off
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DamageStat {
+    #[default]
+    Strength,
+    Intellect,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "op")]
+pub enum SkillEffect {
+    Damage {
+        power: i32,  // Percent of the user's attack (Or magic) stat
+        #[serde(default)]
+        stat: DamageStat,
+    },
+    Heal {
+        power: i32,  // Percent of the user's healing stat
+        #[serde(default)]
+        flat: i32,
+    },
+    ApplyStatus {
+        #[serde(flatten)]
+        inflict: StatusInflict,
+    },
+    RemoveStatus {
+        #[serde(default)]
+        kinds: Vec<StatusKind>,  // Empty clears every ailment
+    },
+    Revive {
+        percent: i32,  // Of max HP
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SkillPrereq {
+    pub skill: String,
+    pub level: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SkillDef {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub cost: i32,  // TP
+    pub target: Targeting,
+    #[serde(default)]
+    pub usable: Usable,
+    #[serde(default = "default_max_level")]
+    pub max_level: u32,
+    #[serde(default)]
+    pub requires: Vec<SkillPrereq>,
+    pub effects: Vec<SkillEffect>,
+}
+
+fn default_max_level() -> u32 { 5 }
+
+impl SkillDef {
+    pub fn targets_dead(&self) -> bool {
+        revives(&self.effects)
+    }
+}
+
+// Dead allies are only valid targets for effects that bring them back
+pub fn revives(effects: &[SkillEffect]) -> bool {
+    effects.iter().any(|e| matches!(e, SkillEffect::Revive { .. }))
+}
+
+#[derive(Resource, Default)]
+pub struct SkillLibrary {
+    pub skills: HashMap<String, SkillDef>,
+}
+
+impl SkillLibrary {
+    pub fn load_from_file(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Unable to open {}: {}", path, e))?;
+        let skills: Vec<SkillDef> = serde_json::from_reader(file)
+            .map_err(|e| format!("Unable to parse {}: {}", path, e))?;
+        Ok(SkillLibrary { skills: skills.into_iter().map(|s| (s.id.clone(), s)).collect() })
+    }
+
+    pub fn get(&self, id: &str) -> Option<&SkillDef> {
+        self.skills.get(id)
+    }
+}
+
+pub fn load_skill_library(mut commands: Commands) {
+    match SkillLibrary::load_from_file(SKILL_FILE) {
+        Ok(lib) => commands.insert_resource(lib),
+        Err(e) => {
+            println!("{} - no skills available", e);
+            commands.insert_resource(SkillLibrary::default());
+        }
+    }
+}
+
+// Numbers from whoever is using the skill
+#[derive(Debug, Clone)]
+pub struct SkillUser {
+    pub name: String,
+    pub attack: i32,
+    pub magic: i32,
+    pub healing: i32,
+}
+
+impl SkillUser {
+    pub fn from_combatant(combatant: &Combatant) -> Self {
+        SkillUser {
+            name: combatant.name.clone(),
+            attack: combatant.effective_attack(),
+            magic: combatant.magic,
+            healing: combatant.healing,
+        }
+    }
+
+    pub fn from_character(character: &Character) -> Self {
+        SkillUser {
+            name: character.name.clone(),
+            attack: character.attack(),
+            magic: character.magic(),
+            healing: character.healing(),
+        }
+    }
+}
+
+pub trait SkillTarget {
+    fn target_name(&self) -> &str;
+    fn is_alive(&self) -> bool;
+    fn max_hp(&self) -> i32;
+    fn target_defense(&self) -> i32;
+    fn guarding(&self) -> bool;
+    fn take_hit(&mut self, amount: i32) -> i32;
+    fn restore_hp(&mut self, amount: i32) -> i32;
+    fn revive_with(&mut self, hp: i32) -> bool;
+    fn status_mut(&mut self) -> &mut StatusEffects;
+}
+
+impl SkillTarget for Combatant {
+    fn target_name(&self) -> &str { &self.name }
+    fn is_alive(&self) -> bool { Combatant::is_alive(self) }
+    fn max_hp(&self) -> i32 { self.max_hp }
+    fn target_defense(&self) -> i32 { self.effective_defense() }
+    fn guarding(&self) -> bool { self.defending }
+    fn take_hit(&mut self, amount: i32) -> i32 { self.take_damage(amount) }
+    fn restore_hp(&mut self, amount: i32) -> i32 {
+        if !Combatant::is_alive(self) {
+            return 0;
+        }
+        let healed = amount.max(0).min(self.max_hp - self.hp);
+        self.hp += healed;
+        healed
+    }
+    fn revive_with(&mut self, hp: i32) -> bool {
+        if Combatant::is_alive(self) {
+            return false;
+        }
+        self.hp = hp.clamp(1, self.max_hp);
+        true
+    }
+    fn status_mut(&mut self) -> &mut StatusEffects { &mut self.status }
+}
+
+impl SkillTarget for Character {
+    fn target_name(&self) -> &str { &self.name }
+    fn is_alive(&self) -> bool { Character::is_alive(self) }
+    fn max_hp(&self) -> i32 { self.health.max_hp() }
+    fn target_defense(&self) -> i32 { self.defense() }
+    fn guarding(&self) -> bool { false }
+    fn take_hit(&mut self, amount: i32) -> i32 { self.health.damage(amount) }
+    fn restore_hp(&mut self, amount: i32) -> i32 { self.health.heal(amount) }
+    fn revive_with(&mut self, hp: i32) -> bool {
+        if Character::is_alive(self) {
+            return false;
+        }
+        self.health.revive(hp);
+        true
+    }
+    fn status_mut(&mut self) -> &mut StatusEffects { &mut self.status }
+}
+
+// +10% power per level past the first
+fn scale(value: i32, level: u32) -> i32 {
+    value * (100 + 10 * (level.max(1) as i32 - 1)) / 100
+}
+
+fn heal_value(power: i32, flat: i32, level: u32, user: &SkillUser) -> i32 {
+    scale(user.healing * power / 100, level) + flat
+}
+
+// Total HP a set of effects restores to one target - healing has no random spread, so this is exact
+pub fn heal_amount(effects: &[SkillEffect], level: u32, user: &SkillUser) -> i32 {
+    effects.iter()
+        .map(|e| match e {
+            SkillEffect::Heal { power, flat } => heal_value(*power, *flat, level, user),
+            _ => 0,
+        })
+        .sum()
+}
+
+// The effect interpreter - runs a skill's effects against one target and returns a log of what happened
+pub fn apply_effects<T: SkillTarget>(
+    effects: &[SkillEffect],
+    level: u32,
+    user: &SkillUser,
+    target: &mut T,
+    rng: &mut GameRng,
+) -> Vec<String> {
+    let mut log = Vec::new();
+    for effect in effects {
+        let name = target.target_name().to_string();
+        match effect {
+            SkillEffect::Revive { percent } => {
+                let hp = target.max_hp() * percent / 100;
+                if target.revive_with(hp) {
+                    log.push(format!("{} is back on their feet!", name));
+                }
+                continue;
+            }
+            _ if !target.is_alive() => continue,
+            SkillEffect::Damage { power, stat } => {
+                let (base, defense) = match stat {
+                    DamageStat::Strength => (user.attack, target.target_defense() / 2),
+                    DamageStat::Intellect => (user.magic, target.target_defense() / 4),
+                };
+                let mut damage = ((scale(base * power / 100, level) - defense).max(1) * rng.range(90, 111) / 100).max(1);
+                // Same rule as a regular attack - guarding halves it
+                if target.guarding() {
+                    damage = (damage / 2).max(1);
+                }
+                let taken = target.take_hit(damage);
+                log.push(format!("{} takes {} damage", name, taken));
+                if !target.is_alive() {
+                    log.push(format!("{} is defeated!", name));
+                } else if target.status_mut().remove(StatusKind::Sleep) {
+                    log.push(format!("{} wakes up!", name));
+                }
+            }
+            SkillEffect::Heal { power, flat } => {
+                let healed = target.restore_hp(heal_value(*power, *flat, level, user));
+                log.push(format!("{} recovers {} HP", name, healed));
+            }
+            SkillEffect::ApplyStatus { inflict } => {
+                let chance = inflict.chance + 5 * (level.max(1) as i32 - 1);
+                if rng.roll(chance) && target.status_mut().apply(inflict.effect) {
+                    log.push(format!("{} is afflicted with {}!", name, inflict.effect.kind.label()));
+                }
+            }
+            SkillEffect::RemoveStatus { kinds } => {
+                let status = target.status_mut();
+                let before = status.effects.len();
+                if kinds.is_empty() {
+                    status.clear_ailments();
+                } else {
+                    for kind in kinds {
+                        status.remove(*kind);
+                    }
+                }
+                if status.effects.len() != before {
+                    log.push(format!("{} feels better", name));
+                }
+            }
+        }
+    }
+    log
+}
+
+// Field use - casts a skill from one party member onto others, outside of combat
+// `targets` are party slots, already worked out from the skill's targeting
+pub fn use_skill_in_field(
+    members: &mut [Character],
+    user: usize,
+    skill: &SkillDef,
+    targets: &[usize],
+    rng: &mut GameRng,
+) -> Result<Vec<String>, String> {
+    if !skill.usable.in_field() {
+        return Err(format!("{} can't be used outside of combat", skill.name));
+    }
+    if skill.target.side == TargetSide::Enemy {
+        return Err(format!("{} needs an enemy to target", skill.name));
+    }
+    let caster = members.get(user).ok_or(format!("No member at slot {}", user))?;
+    if !caster.is_alive() {
+        return Err(format!("{} can't act", caster.name));
+    }
+    let level = caster.skill_level(&skill.id);
+    if level == 0 {
+        return Err(format!("{} doesn't know {}", caster.name, skill.name));
+    }
+    if caster.tp < skill.cost {
+        return Err(format!("{} doesn't have enough TP", caster.name));
+    }
+
+    let caster_stats = SkillUser::from_character(caster);
+    members[user].tp -= skill.cost;
+    let mut log = vec![format!("{} uses {}", caster_stats.name, skill.name)];
+    for slot in targets {
+        let Some(target) = members.get_mut(*slot) else { continue };
+        log.extend(apply_effects(&skill.effects, level, &caster_stats, target, rng));
+    }
+    Ok(log)
+}
@@ -0,0 +1,122 @@
+// Shared message box UI - used by scripted events and dialogue
+// Only one box is up at a time, anchored to the bottom of the screen
+
+use bevy::prelude::*;
+
+#[derive(Component)]
+pub struct TextBox;
+
+// Attached to each choice button so clicks map back to an option index
+#[derive(Component, Clone, Copy)]
+pub struct TextBoxChoice(pub usize);
+
+// What the player did with the box - consumers read these instead of raw input
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextBoxInput {
+    Confirm,
+    Choose(usize),
+}
+
+const BOX_COLOR: Color = Color::rgba(0.05, 0.05, 0.15, 0.9);
+const CHOICE_COLOR: Color = Color::rgb(0.2, 0.2, 0.35);
+
+// Spawns the box - with no choices it's a plain message that waits for Confirm
+pub fn spawn_textbox(commands: &mut Commands, speaker: Option<&str>, text: &str, choices: &[String]) {
+    let text_style = TextStyle{
+        font_size: 20.0,
+        color: Color::WHITE,
+        ..default()
+    };
+    let speaker_style = TextStyle{
+        font_size: 20.0,
+        color: Color::GOLD,
+        ..default()
+    };
+
+    commands
+        .spawn((NodeBundle{
+            style: Style{
+                flex_direction: FlexDirection::Column,
+                position_type: PositionType::Absolute,
+                left: Val::Percent(5.),
+                right: Val::Percent(5.),
+                bottom: Val::Px(10.),
+                padding: UiRect::all(Val::Px(12.)),
+                ..default()
+            },
+            background_color: BOX_COLOR.into(),
+            ..default()
+        },
+        TextBox,
+        ))
+        .with_children(|parent| {
+            if let Some(speaker) = speaker {
+                parent.spawn(TextBundle::from_section(speaker, speaker_style.clone()));
+            }
+            parent.spawn(TextBundle::from_section(text, text_style.clone()));
+
+            for (i, choice) in choices.iter().enumerate() {
+                parent
+                    .spawn((ButtonBundle{
+                        style: Style{
+                            margin: UiRect::top(Val::Px(6.)),
+                            padding: UiRect::all(Val::Px(4.)),
+                            ..default()
+                        },
+                        background_color: CHOICE_COLOR.into(),
+                        ..default()
+                    },
+                    TextBoxChoice(i),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(format!("{}. {}", i + 1, choice), text_style.clone()));
+                    });
+            }
+        });
+}
+
+pub fn despawn_textbox(commands: &mut Commands, boxes: &Query<Entity, With<TextBox>>) {
+    for entity in boxes.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+// Translates keyboard/mouse input into TextBoxInput events while a box is up
+// Enter/Space confirm a message, number keys (Or clicking) pick a choice
+pub fn textbox_input(
+    boxes: Query<(), With<TextBox>>,
+    choices: Query<(&Interaction, &TextBoxChoice), Changed<Interaction>>,
+    input: Res<Input<KeyCode>>,
+    mut events: EventWriter<TextBoxInput>,
+) {
+    if boxes.is_empty() {
+        return;
+    }
+    if input.any_just_pressed([KeyCode::Return, KeyCode::Space]) {
+        events.send(TextBoxInput::Confirm);
+    }
+    let number_keys = [
+        KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5,
+        KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+    ];
+    for (i, key) in number_keys.iter().enumerate() {
+        if input.just_pressed(*key) {
+            events.send(TextBoxInput::Choose(i));
+        }
+    }
+    for (interaction, choice) in choices.iter() {
+        if *interaction == Interaction::Pressed {
+            events.send(TextBoxInput::Choose(choice.0));
+        }
+    }
+}
+
+pub struct TextBoxPlugin;
+
+impl Plugin for TextBoxPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_event::<TextBoxInput>()
+            .add_systems(Update, textbox_input);
+    }
+}
@@ -0,0 +1,62 @@
+// Turn loop for exploration
+// AwaitingInput -> PlayerTurn -> EnemyTurn -> OtherTurn -> back to AwaitingInput
+// Each step of the loop is a TurnPhase system set, so other modules register for a phase with:
+//     app.add_systems(Update, my_system.in_set(TurnPhase::Enemy))
+// A phase system can break out of the loop (Combat, dialogue, map change) by setting a different TurnState -
+// the loop only moves itself forward if nothing else has asked for a state change that frame
+
+use bevy::prelude::*;
+
+use crate::resources::*;
+
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TurnPhase {
+    Input,  // Waiting on the player - anything that consumes a turn moves us to PlayerTurn
+    Player, // Results of the player's action (Stepping on tiles, etc...)
+    Enemy,  // Enemies on the map act
+    Other,  // Environmental effects tick (Terrain changes, poison, etc...)
+}
+
+// Number of turns taken since the game started - handy for anything that needs to happen every N turns
+#[derive(Resource, Debug, Clone, Copy, Default, serde::Serialize, serde::Deserialize)]
+pub struct TurnCounter(pub u64);
+
+// Moves the loop forward one phase - runs after every phase's systems
+pub fn advance_turn(
+    state: Res<State<TurnState>>,
+    mut next_turn: ResMut<NextState<TurnState>>,
+) {
+    // Something in the phase already redirected the turn loop (Combat, map transition, etc...)
+    if next_turn.0.is_some() {
+        return;
+    }
+    match state.get() {
+        TurnState::PlayerTurn => next_turn.set(TurnState::EnemyTurn),
+        TurnState::EnemyTurn => next_turn.set(TurnState::OtherTurn),
+        TurnState::OtherTurn => next_turn.set(TurnState::AwaitingInput),
+        _ => {}
+    }
+}
+
+pub fn tick_turn_counter(mut counter: ResMut<TurnCounter>) {
+    counter.0 += 1;
+}
+
+pub struct TurnPlugin;
+
+impl Plugin for TurnPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<TurnCounter>()
+            .configure_sets(Update, (
+                TurnPhase::Input
+                    .run_if(in_state(TurnState::AwaitingInput))
+                    .run_if(in_state(GameplayState::Exploration)),
+                TurnPhase::Player.run_if(in_state(TurnState::PlayerTurn)),
+                TurnPhase::Enemy.run_if(in_state(TurnState::EnemyTurn)),
+                TurnPhase::Other.run_if(in_state(TurnState::OtherTurn)),
+            ).chain())
+            .add_systems(Update, tick_turn_counter.in_set(TurnPhase::Other))
+            .add_systems(Update, advance_turn.after(TurnPhase::Other));
+    }
+}